@@ -6,7 +6,13 @@
 //
 // Query terms are stemmed with the Porter1 algorithm before lookup so that
 // they match the Snowball-stemmed keys stored in the index (e.g. searching
-// for "compilers" matches pages indexed under the stem "compil").
+// for "compilers" matches pages indexed under the stem "compil"). Other
+// languages are stemmed when the site is built, and the stem of each word is
+// looked up in `search_stems.json` instead.
+//
+// Chinese and Japanese text isn't written with spaces between words, so runs
+// of Han, Hiragana, and Katakana characters are indexed as overlapping pairs
+// of characters. Queries are split into pairs in the same way.
 
 // ─── Porter1 stemmer ────────────────────────────────────────────────────────
 // Public-domain implementation of M.F. Porter, "An algorithm for suffix
@@ -115,25 +121,42 @@ const initialiseSearch = async (rootPath, targetSelector) => {
         return;
     }
 
-    searchForm.innerHTML = `<h2>Search</h2>
+    // Interface strings come from the layout, in the page's language.
+    const strings = {
+        search: 'Search',
+        placeholder: 'enter your search',
+        noResults: 'No results',
+        results: '{count} results',
+        ...searchForm.dataset,
+    };
+    searchForm.innerHTML = `<h2></h2>
     <form target="none">
-        <input id="query" autofocus="" placeholder="enter your search" type="search">
-        <button action="submit">Search</button>
+        <input id="query" autofocus="" type="search">
+        <button action="submit"></button>
     </form>
     <div id="docket-search-results"></div>`;
+    searchForm.querySelector('h2').textContent = strings.search;
+    searchForm.querySelector('button').textContent = strings.search;
     const searchBox = searchForm.querySelector('#query');
+    searchBox.placeholder = strings.placeholder;
     const searchResults = searchForm.querySelector('#docket-search-results');
 
+    const resultsHeading = label => {
+        const heading = document.createElement('h3');
+        heading.textContent = label;
+        return heading.outerHTML;
+    }
+
     const searchEntryForResult = result => {
         return `<li><a class="search-result" href="${rootPath}${result.slug}/" >${result.title}</a></li>`;
     }
 
     const displayResults = results => {
         if (results.length == 0) {
-            searchResults.innerHTML = "<h3>No results</h3>";
+            searchResults.innerHTML = resultsHeading(strings.noResults);
         } else {
             searchResults.innerHTML =
-                `<h3>${results.length} results</h3>
+                `${resultsHeading(strings.results.replace('{count}', results.length))}
                 <ul class="search-results">
                     ${results.map(searchEntryForResult).join('')}
                 </ul>`;
@@ -143,6 +166,28 @@ const initialiseSearch = async (rootPath, targetSelector) => {
     const searchIndex = await fetch(`${rootPath}search_index.json`)
         .then(response => response.json());
 
+    const lang = (document.documentElement.lang || 'en').toLowerCase();
+    const stems = lang.startsWith('en') ? null : await fetch(`${rootPath}search_stems.json`)
+        .then(response => response.ok ? response.json() : {})
+        .catch(() => ({}));
+    const stemTerm = term => stems ? (stems[term] ?? term) : stemWord(term);
+
+    // These ranges must match `is_cjk` in `search.rs`.
+    const cjk = /[\u3005-\u3007\u3040-\u30ff\u31f0-\u31ff\u3400-\u4dbf\u4e00-\u9fff\uf900-\ufaff\uff66-\uff9f\u{20000}-\u{2ffff}]+/gu;
+    const bigrams = run => {
+        const chars = [...run];
+        return chars.length < 2 ? chars : chars.slice(1).map((c, i) => chars[i] + c);
+    };
+
+    // Split a word into the terms it was indexed under.
+    const wordTerms = word => {
+        const terms = (word.match(cjk) ?? []).flatMap(bigrams);
+        word.split(cjk)
+            .filter(term => [...term].length >= 3)
+            .forEach(term => terms.push(stemTerm(term)));
+        return terms;
+    };
+
     const doSearch = query => {
 
         // If the search is empty clean up.
@@ -151,11 +196,10 @@ const initialiseSearch = async (rootPath, targetSelector) => {
             return;
         }
 
-        // Split, lowercase, filter short tokens, then stem to match the index.
-        let terms = query.split(/\W+/)
+        // Split, lowercase, then break into terms to match the index.
+        let terms = query.split(/[^\p{L}\p{N}]+/u)
             .map(term => term.trim().toLowerCase())
-            .filter(term => term.length >= 3)
-            .map(term => stemWord(term));
+            .flatMap(wordTerms);
         let found = []
 
         searchIndex.forEach(page => {
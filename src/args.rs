@@ -3,6 +3,8 @@
 //! This module defines the command line usage text and provides a method to
 //! load the arguments from the command line.
 
+use std::path::PathBuf;
+
 use docopt::Docopt;
 use serde::Deserialize;
 
@@ -17,11 +19,14 @@ Docket Documentation Generator
 Usage: docket [options]
 
 Options:
-  --version           Show the version.
-  -h --help           Show this screen.
-  -s, --source=<in>   Documentation directory, default is current directory.
-  -t, --target=<out>  Write the output to <out>, default is `./build/`.
-  -w, --watch         Watch for changes and re-generate.
+  --version             Show the version.
+  -h --help             Show this screen.
+  -s, --source=<in>     Documentation directory, default is current directory.
+  -t, --target=<out>    Write the output to <out>, default is `./build/`.
+  -a, --archive=<file>  Write the output to a `.zip` or `.tar` archive instead.
+  -c, --clean           Remove files in the target not written by the build.
+  --strict              Treat warnings as errors.
+  -w, --watch           Watch for changes and re-generate.
 ";
 
 /// Program Arguments
@@ -31,8 +36,11 @@ Options:
 #[derive(Debug, Deserialize)]
 pub(crate) struct Args {
     pub flag_watch: bool,
+    pub flag_clean: bool,
+    pub flag_strict: bool,
     pub flag_source: Option<String>,
     pub flag_target: Option<String>,
+    pub flag_archive: Option<String>,
 }
 
 /// Parse the arguments from a given command line
@@ -53,21 +61,38 @@ pub(crate) fn from_command_line() -> Args {
     from_argv(argv).unwrap_or_else(|e| e.exit())
 }
 
+/// Path or Default
+///
+/// Try to convert a command line argument to a path. Falling back to the
+/// default if none is provided.
+pub(crate) fn path_or_default(maybe_path: Option<String>, default: &str) -> PathBuf {
+    maybe_path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default.to_owned().into())
+}
+
 #[cfg(test)]
 mod test {
-    use super::from_argv;
+    use super::{from_argv, path_or_default};
+    use std::path::Path;
 
     #[test]
     fn parse_empty_argv() {
         let args = from_argv::<&[String], &String>(&[]).unwrap();
         assert_eq!(None, args.flag_source);
         assert_eq!(None, args.flag_target);
+        assert_eq!(None, args.flag_archive);
         assert_eq!(false, args.flag_watch);
+        assert_eq!(false, args.flag_clean);
+        assert_eq!(false, args.flag_strict);
 
         let args = from_argv(&["docket"]).unwrap();
         assert_eq!(None, args.flag_source);
         assert_eq!(None, args.flag_target);
+        assert_eq!(None, args.flag_archive);
         assert_eq!(false, args.flag_watch);
+        assert_eq!(false, args.flag_clean);
+        assert_eq!(false, args.flag_strict);
     }
 
     #[test]
@@ -95,4 +120,43 @@ mod test {
         assert_eq!(Some("../another"), args.flag_target.as_deref());
         assert_eq!(true, args.flag_watch);
     }
+
+    #[test]
+    fn parse_clean_flag() {
+        let args = from_argv(["docket", "--clean"]).unwrap();
+        assert_eq!(true, args.flag_clean);
+
+        let args = from_argv(["docket", "-c", "-t", "out/"]).unwrap();
+        assert_eq!(true, args.flag_clean);
+        assert_eq!(Some("out/"), args.flag_target.as_deref());
+    }
+
+    #[test]
+    fn parse_strict_flag() {
+        let args = from_argv(["docket", "--strict", "-s", "docs/"]).unwrap();
+        assert_eq!(true, args.flag_strict);
+        assert_eq!(Some("docs/"), args.flag_source.as_deref());
+    }
+
+    #[test]
+    fn parse_archive_option() {
+        let args = from_argv(["docket", "-s", "docs/", "--archive", "site.zip"]).unwrap();
+        assert_eq!(Some("docs/"), args.flag_source.as_deref());
+        assert_eq!(Some("site.zip"), args.flag_archive.as_deref());
+
+        let args = from_argv(["docket", "-a", "out/site.tar"]).unwrap();
+        assert_eq!(Some("out/site.tar"), args.flag_archive.as_deref());
+    }
+
+    #[test]
+    fn path_or_default_with_valid_argument() {
+        let source = Some("/Users/foo/".to_owned());
+        assert_eq!(Path::new("/Users/foo/"), path_or_default(source, "."));
+    }
+
+    #[test]
+    fn path_or_default_without_argument() {
+        let source = None;
+        assert_eq!(Path::new("baz/"), path_or_default(source, "baz/"));
+    }
 }
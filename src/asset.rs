@@ -4,13 +4,10 @@
 //! This module models the other files in a given docs directory which
 //! need to be copied to the output. We use this to abstract between
 //! 'bulitin' assets, such as the CSS which is bundled with Docket
-//! itself and on-disk assets from the source directory.
-
-use log::warn;
+//! itself and assets from the source directory.
 
 use super::Result;
-use std::fs::{self, File};
-use std::io::prelude::*;
+use crate::{diagnostics::Diagnostic, error::Error, output::OutputSink, source::Source};
 use std::path::{Path, PathBuf};
 
 /// Defines a Documentation Asset
@@ -19,11 +16,11 @@ use std::path::{Path, PathBuf};
 /// directory to support the rendered markdown files.
 #[derive(Debug)]
 pub enum Asset {
-    /// On-Disk Asset
+    /// Source Asset
     ///
-    /// On disk assets come from the source directory and are file-copied
-    /// to the output when the asset is rendered.
-    Disk(PathBuf),
+    /// Source assets come from the source directory and are copied from the
+    /// source file system to the output when the asset is rendered.
+    Source(PathBuf),
 
     /// Internal Asset
     ///
@@ -76,7 +73,7 @@ impl Asset {
     /// # Parameters
     ///  * `path` - The source path
     pub const fn path(path: PathBuf) -> Self {
-        Asset::Disk(path)
+        Asset::Source(path)
     }
 
     /// Create a Generated Asset
@@ -94,48 +91,44 @@ impl Asset {
 
     /// Copy To
     ///
-    /// This method is called to copy a given asset to the output
-    /// directory.
-    pub fn copy_to(&self, output: &Path) -> Result<()> {
+    /// This method is called to write a given asset to the `dir` within the
+    /// site output. Source assets are read from the given `source`, and any
+    /// that can't be copied are reported to it.
+    pub(crate) fn copy_to(
+        &self,
+        source: &Source,
+        output: &dyn OutputSink,
+        dir: &Path,
+    ) -> Result<()> {
         match self {
-            Asset::Internal(int) => {
-                let path = output.join(int.name);
-                let mut file = File::create(&path)?;
-                write!(file, "{}", int.contents)?;
-                Ok(())
-            }
-            Asset::Generated(gen) => {
-                let path = output.join(&gen.name);
-                let mut file = File::create(&path)?;
-                write!(file, "{}", gen.contents)?;
-                Ok(())
-            }
-            Asset::Disk(path) => copy_single(&path, output),
+            Asset::Internal(int) => output.write(&dir.join(int.name), int.contents.as_bytes())?,
+            Asset::Generated(gen) => output.write(&dir.join(&gen.name), gen.contents.as_bytes())?,
+            Asset::Source(path) => copy_single(source, output, path, dir)?,
         }
+        Ok(())
     }
 }
 
-fn copy_single(path: &Path, target: &Path) -> Result<()> {
-    if path.is_dir() {
-        let mut target = PathBuf::from(target);
-        target.push(path.file_name().unwrap());
-        if !target.exists() {
-            fs::create_dir(&target)?;
+fn copy_single(source: &Source, output: &dyn OutputSink, path: &Path, target: &Path) -> Result<()> {
+    let fs = source.fs();
+    match path.file_name() {
+        Some(name) if fs.is_dir(path) => copy_recurse(source, output, path, &target.join(name))?,
+        Some(name) => {
+            let contents = fs.read(path).map_err(|e| Error::in_file(path, e))?;
+            output.write(&target.join(name), &contents)?;
         }
-        copy_recurse(path, &target)?;
-    } else if let Some(name) = path.file_name() {
-        fs::copy(path, target.join(name))?;
-    } else {
-        warn!("Asset at {:?} does not appear to be copyable", path);
+        None => source.report(Diagnostic::warning("asset can't be copied").in_file(path)),
     }
     Ok(())
 }
 
-fn copy_recurse(source: &Path, target: &Path) -> Result<()> {
-    for entry in fs::read_dir(source)? {
-        let entry = entry?;
-        let path = entry.path();
-        copy_single(&path, target)?;
+fn copy_recurse(source: &Source, output: &dyn OutputSink, dir: &Path, target: &Path) -> Result<()> {
+    let entries = source
+        .fs()
+        .read_dir(dir)
+        .map_err(|e| Error::in_file(dir, e))?;
+    for path in entries {
+        copy_single(source, output, &path, target)?;
     }
     Ok(())
 }
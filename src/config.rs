@@ -0,0 +1,449 @@
+//! Site Configuration
+//!
+//! Docket is mostly configured by convention: titles come from headings, URLs
+//! from file names, and the footer from `footer.md`. Settings that can't be
+//! inferred from the source tree are read from an optional `docket.toml` file
+//! in the root of the documentation directory.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    locale,
+    source::SourceFs,
+    utils,
+};
+
+/// The name of the configuration file in the documentation root.
+pub(crate) const CONFIG_FILE_NAME: &str = "docket.toml";
+
+/// The name of the directory in the documentation root which holds custom
+/// templates for the layout.
+pub(crate) const TEMPLATES_DIR_NAME: &str = "_templates";
+
+/// Site Configuration
+///
+/// Deserialised from `docket.toml`. Every setting is optional, and the default
+/// configuration is used if no file is present.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct Config {
+    /// External diagram renderers. Maps a fenced code block language to the
+    /// command used to render it to SVG, e.g. `dot = "dot -Tsvg"`.
+    pub diagrams: HashMap<String, String>,
+
+    /// Template for links to each page's source. `{path}` is replaced with the
+    /// path to the page's source file, relative to the documentation root.
+    pub edit_url: Option<String>,
+
+    /// The public URL the site is hosted at, e.g. `https://example.com/docs/`.
+    /// When set a `sitemap.xml` listing every page is written.
+    pub base_url: Option<String>,
+
+    /// Write a `robots.txt` pointing to the sitemap. Needs `base_url`.
+    pub robots_txt: bool,
+
+    /// The language the documentation is written in, as a BCP 47 tag
+    pub lang: String,
+
+    /// The languages the documentation is translated into, mapping each
+    /// language's tag to its name, e.g. `ja = "日本語"`. The default `lang`
+    /// can be named here too.
+    pub languages: BTreeMap<String, String>,
+
+    /// Use the default language's page where a translation is missing
+    pub translation_fallback: bool,
+
+    /// File of interface strings for the layout, relative to the
+    /// documentation root. Defaults to `strings.toml`, if it exists.
+    pub strings: Option<PathBuf>,
+
+    /// Bales to publish Atom feeds for, as paths relative to the
+    /// documentation root, e.g. `["news"]`.
+    pub feeds: Vec<PathBuf>,
+
+    /// The author of the documentation, named in feeds. Defaults to the
+    /// site's name.
+    pub author: Option<String>,
+
+    /// Settings for reading page history from git
+    pub git: GitConfig,
+
+    /// How slugs are made for pages, bales, and headings
+    pub slugs: SlugConfig,
+
+    /// Redirects from old paths to new ones, both relative to the site root,
+    /// e.g. `"old-name/" = "guide/new-name/"`.
+    pub redirects: BTreeMap<String, String>,
+
+    /// Extra stylesheets to link from every page, relative to the
+    /// documentation root. Defaults to `extra.css`, if it exists.
+    pub extra_css: Option<Vec<PathBuf>>,
+
+    /// Extra scripts to link from every page, relative to the documentation
+    /// root. Defaults to `extra.js`, if it exists.
+    pub extra_js: Option<Vec<PathBuf>>,
+
+    /// Only link to resources within the output. When set web fonts aren't
+    /// loaded, and code isn't highlighted by scripts from a CDN.
+    pub offline: bool,
+
+    /// Versions of the documentation to build from git, e.g. one for each
+    /// release. When set each version is rendered into its own subdirectory.
+    pub versions: Vec<VersionConfig>,
+
+    /// The number of levels of the navigation tree to show. Bales below this
+    /// depth are shown as links, without their contents. Defaults to showing
+    /// the whole tree.
+    pub nav_depth: Option<NonZeroUsize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            diagrams: HashMap::new(),
+            edit_url: None,
+            base_url: None,
+            robots_txt: false,
+            lang: String::from("en"),
+            languages: BTreeMap::new(),
+            translation_fallback: false,
+            strings: None,
+            feeds: Vec::new(),
+            author: None,
+            git: GitConfig::default(),
+            slugs: SlugConfig::default(),
+            redirects: BTreeMap::new(),
+            extra_css: None,
+            extra_js: None,
+            offline: true,
+            versions: Vec::new(),
+            nav_depth: None,
+        }
+    }
+}
+
+/// Git History Configuration
+///
+/// Controls which page metadata is read from the git repository containing
+/// the documentation, rather than from the file system.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct GitConfig {
+    /// Use the date of the last commit to each page as its last-updated date
+    pub last_updated: bool,
+    /// List the authors of the commits to each page as its contributors
+    pub contributors: bool,
+}
+
+impl GitConfig {
+    /// Check if any setting needs the git history to be loaded
+    pub fn enabled(&self) -> bool {
+        self.last_updated || self.contributors
+    }
+}
+
+/// Version Configuration
+///
+/// A version of the documentation, read from the git repository containing it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct VersionConfig {
+    /// The name of the version, and the subdirectory it is rendered to
+    pub name: String,
+    /// The tag, branch, or commit to read the version's documentation from
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+}
+
+/// Check a `name` can be used as a single directory name in URLs
+fn is_directory_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+/// Slug Configuration
+///
+/// Controls how the slugs used in URLs and heading ids are made from file
+/// names and heading text. By default slugs are lowercase ASCII.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct SlugConfig {
+    /// Downcase slugs
+    pub lowercase: bool,
+    /// Transliterate accented letters to ASCII, and replace any other
+    /// characters which aren't URL-safe with dashes
+    pub ascii: bool,
+    /// Collapse runs of dashes, and trim them from either end
+    pub collapse_dashes: bool,
+}
+
+impl Default for SlugConfig {
+    fn default() -> Self {
+        SlugConfig {
+            lowercase: true,
+            ascii: true,
+            collapse_dashes: true,
+        }
+    }
+}
+
+impl SlugConfig {
+    /// Slugify a String
+    ///
+    /// Converts `input` to a slug with this policy.
+    pub fn slugify<S: AsRef<str>>(&self, input: S) -> String {
+        self.apply(utils::slugify(input))
+    }
+
+    /// Slugify a Path
+    ///
+    /// Makes a slug from the file stem of `path` with this policy.
+    pub fn slugify_path<P: AsRef<Path>>(&self, path: P) -> String {
+        self.apply(utils::slugify_path(path))
+    }
+
+    /// Apply the policy to a `slug`. If transliterating would leave nothing,
+    /// such as for text in a non-Latin script, the slug is kept as it is.
+    fn apply(&self, mut slug: String) -> String {
+        if self.lowercase {
+            slug = slug.to_lowercase();
+        }
+        if self.ascii {
+            let ascii = utils::ascii_slug(&slug);
+            if ascii.chars().any(|c| c != '-') {
+                slug = ascii;
+            }
+        }
+        if self.collapse_dashes {
+            slug = utils::collapse_dashes(slug);
+        }
+        slug
+    }
+}
+
+impl Config {
+    /// Load the Configuration for a Documentation Root
+    ///
+    /// Reads `docket.toml` from the given directory, if there is one. If no
+    /// configuration file exists the default configuration is returned.
+    pub fn load(fs: &dyn SourceFs, root: &Path) -> Result<Self> {
+        let path = root.join(CONFIG_FILE_NAME);
+        if !fs.is_file(&path) {
+            return Ok(Config::default());
+        }
+        let contents = fs.read_to_string(&path)?;
+        let config: Config = toml::from_str(&contents)
+            .map_err(|e| Error::InvalidConfig(path.clone(), e.to_string()))?;
+        let invalid = config
+            .versions
+            .iter()
+            .map(|version| ("version name", &version.name))
+            .chain(config.languages.keys().map(|lang| ("language", lang)))
+            .find(|(_, name)| !is_directory_name(name));
+        if let Some((kind, name)) = invalid {
+            return Err(Error::InvalidConfig(
+                path,
+                format!("{} `{}` isn't a valid directory name", kind, name),
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Get the path of the interface strings file, relative to the
+    /// documentation root
+    pub fn strings_file(&self) -> &Path {
+        self.strings
+            .as_deref()
+            .unwrap_or(Path::new(locale::STRINGS_FILE_NAME))
+    }
+
+    /// Get the Edit Link for a Source File
+    ///
+    /// Expands the `edit_url` template for the given relative `path`, if an
+    /// edit URL has been configured.
+    pub fn edit_url_for(&self, path: &Path) -> Option<String> {
+        self.edit_url
+            .as_ref()
+            .map(|template| template.replace("{path}", &utils::url_encode_path(path)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_empty_config() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.diagrams.is_empty());
+        assert_eq!(None, config.edit_url_for(Path::new("index.md")));
+        assert!(!config.git.enabled());
+        assert_eq!(None, config.extra_css);
+        assert_eq!(None, config.extra_js);
+        assert!(config.offline);
+        assert!(config.redirects.is_empty());
+        assert_eq!(None, config.base_url);
+        assert!(!config.robots_txt);
+        assert_eq!("en", config.lang);
+        assert!(config.feeds.is_empty());
+        assert!(config.versions.is_empty());
+        assert!(config.languages.is_empty());
+        assert!(!config.translation_fallback);
+        assert_eq!(Path::new("strings.toml"), config.strings_file());
+        assert_eq!(None, config.nav_depth);
+    }
+
+    #[test]
+    fn default_slug_policy() {
+        let slugs = Config::default().slugs;
+        assert_eq!("hello-world", slugs.slugify("Hello  World"));
+        assert_eq!("creme-brulee", slugs.slugify("Crème Brûlée?"));
+        assert_eq!("price-eur", slugs.slugify("Price (€) - EUR"));
+        assert_eq!("about", slugs.slugify_path("docs/01-About.md"));
+        assert_eq!("日本語", slugs.slugify("日本語"));
+    }
+
+    #[test]
+    fn parse_slug_policy() {
+        let config: Config =
+            toml::from_str("[slugs]\nlowercase = false\nascii = false\ncollapse-dashes = false\n")
+                .unwrap();
+        assert_eq!("Hello--World", config.slugs.slugify("Hello  World"));
+        assert_eq!("€$§±*", config.slugs.slugify("€$§±*"));
+    }
+
+    #[test]
+    fn parse_online_config() {
+        let config: Config = toml::from_str("offline = false\n").unwrap();
+        assert!(!config.offline);
+    }
+
+    #[test]
+    fn parse_nav_depth() {
+        let config: Config = toml::from_str("nav-depth = 2\n").unwrap();
+        assert_eq!(NonZeroUsize::new(2), config.nav_depth);
+        assert!(toml::from_str::<Config>("nav-depth = 0\n").is_err());
+    }
+
+    #[test]
+    fn parse_extra_assets() {
+        let config: Config =
+            toml::from_str("extra-css = [\"theme/site.css\", \"print.css\"]\nextra-js = []\n")
+                .unwrap();
+        assert_eq!(
+            Some(vec![
+                PathBuf::from("theme/site.css"),
+                PathBuf::from("print.css")
+            ]),
+            config.extra_css
+        );
+        assert_eq!(Some(Vec::new()), config.extra_js);
+    }
+
+    #[test]
+    fn expand_edit_url() {
+        let config: Config =
+            toml::from_str(r#"edit-url = "https://example.com/edit/main/docs/{path}""#).unwrap();
+        assert_eq!(
+            Some("https://example.com/edit/main/docs/guide/01-getting%20started.md"),
+            config
+                .edit_url_for(Path::new("guide/01-getting started.md"))
+                .as_deref()
+        );
+    }
+
+    #[test]
+    fn parse_diagram_commands() {
+        let config: Config = toml::from_str(
+            r#"
+[diagrams]
+dot = "dot -Tsvg"
+mermaid = "mmdc -i - -o - -e svg"
+"#,
+        )
+        .unwrap();
+        assert_eq!(2, config.diagrams.len());
+        assert_eq!(
+            Some("dot -Tsvg"),
+            config.diagrams.get("dot").map(|s| &s[..])
+        );
+    }
+
+    #[test]
+    fn parse_redirects() {
+        let config: Config =
+            toml::from_str("[redirects]\n\"old-name/\" = \"guide/new-name/\"\n").unwrap();
+        assert_eq!(
+            Some("guide/new-name/"),
+            config.redirects.get("old-name/").map(|s| &s[..])
+        );
+    }
+
+    #[test]
+    fn parse_versions() {
+        let config: Config = toml::from_str(
+            r#"
+[[versions]]
+name = "latest"
+ref = "main"
+
+[[versions]]
+name = "v1"
+ref = "v1.0.0"
+"#,
+        )
+        .unwrap();
+        let versions: Vec<_> = config
+            .versions
+            .iter()
+            .map(|v| (&v.name[..], &v.git_ref[..]))
+            .collect();
+        assert_eq!(vec![("latest", "main"), ("v1", "v1.0.0")], versions);
+        assert!(config.versions.iter().all(|v| is_directory_name(&v.name)));
+    }
+
+    #[test]
+    fn reject_invalid_version_names() {
+        let fs: crate::source::MemoryFs = [(
+            "docs/docket.toml",
+            "[[versions]]\nname = \"v1/beta\"\nref = \"main\"\n",
+        )]
+        .into_iter()
+        .collect();
+        match Config::load(&fs, Path::new("docs")) {
+            Err(Error::InvalidConfig(path, message)) => {
+                assert_eq!(Path::new("docs/docket.toml"), path);
+                assert_eq!(
+                    "version name `v1/beta` isn't a valid directory name",
+                    message
+                );
+            }
+            other => panic!("Expected invalid config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_languages() {
+        let config: Config = toml::from_str(
+            "lang = \"en\"\ntranslation-fallback = true\n\n[languages]\nen = \"English\"\nja = \"日本語\"\n",
+        )
+        .unwrap();
+        assert_eq!(Some("日本語"), config.languages.get("ja").map(|s| &s[..]));
+        assert_eq!(2, config.languages.len());
+        assert!(config.translation_fallback);
+    }
+
+    #[test]
+    fn parse_git_settings() {
+        let config: Config = toml::from_str("[git]\ncontributors = true\n").unwrap();
+        assert!(!config.git.last_updated);
+        assert!(config.git.contributors);
+        assert!(config.git.enabled());
+    }
+}
@@ -0,0 +1,222 @@
+//! Build Diagnostics
+//!
+//! Problems found while building a site are recorded as `Diagnostic`s. Each
+//! diagnostic has a severity, a message, and where known the file and position
+//! within that file the problem was found at. Warnings don't stop the build;
+//! they are collected as the site is rendered and returned once it is
+//! complete so they can be reported together.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// A problem which doesn't stop the site being built
+    Warning,
+    /// A problem which stops the site being built
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A line and column within a source file. Both start from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    /// The line number
+    pub line: usize,
+    /// The column, in characters
+    pub column: usize,
+}
+
+impl Position {
+    /// Get the Position of a Byte Offset
+    ///
+    /// Converts an `offset` into `text`, such as the ranges given by
+    /// pulldown-cmark's offset iterator, into a line and column.
+    pub fn from_offset(text: &str, offset: usize) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        Position {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// Diagnostic
+///
+/// A single problem found while building the site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    path: Option<PathBuf>,
+    position: Option<Position>,
+}
+
+impl Diagnostic {
+    /// Create a Warning with the given `message`
+    pub fn warning<S: Into<String>>(message: S) -> Self {
+        Self::new(Severity::Warning, message.into())
+    }
+
+    /// Create an Error with the given `message`
+    pub fn error<S: Into<String>>(message: S) -> Self {
+        Self::new(Severity::Error, message.into())
+    }
+
+    fn new(severity: Severity, message: String) -> Self {
+        Diagnostic {
+            severity,
+            message,
+            path: None,
+            position: None,
+        }
+    }
+
+    /// Set the file the diagnostic refers to
+    pub fn in_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Set the position within the file the diagnostic refers to
+    pub fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Get the severity of the diagnostic
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Get the message describing the problem
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the file the diagnostic refers to, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Get the position within the file, if known
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Write the file and position the diagnostic refers to, if any, as a
+    /// prefix for the message
+    pub(crate) fn fmt_location(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path.display())?;
+            if let Some(position) = self.position {
+                write!(f, "{}:{}:", position.line, position.column)?;
+            }
+            f.write_str(" ")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_location(f)?;
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Diagnostic Collector
+///
+/// Gathers the diagnostics reported during a build. Parts of the doctree may be
+/// opened more than once, so repeated diagnostics are only kept once.
+#[derive(Debug, Default)]
+pub(crate) struct Diagnostics(RefCell<Vec<Diagnostic>>);
+
+impl Diagnostics {
+    /// Record a diagnostic
+    pub fn report(&self, diagnostic: Diagnostic) {
+        let mut diagnostics = self.0.borrow_mut();
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Take the diagnostics recorded so far
+    pub fn take(&self) -> Vec<Diagnostic> {
+        self.0.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn position_from_offset() {
+        let text = "# Title\n\nSome `code` here\n→ x";
+        assert_eq!(
+            Position { line: 1, column: 1 },
+            Position::from_offset(text, 0)
+        );
+        assert_eq!(
+            Position { line: 1, column: 3 },
+            Position::from_offset(text, 2)
+        );
+        assert_eq!(
+            Position { line: 3, column: 6 },
+            Position::from_offset(text, 14)
+        );
+        assert_eq!(
+            Position { line: 4, column: 3 },
+            Position::from_offset(text, 30)
+        );
+        assert_eq!(
+            Position { line: 4, column: 4 },
+            Position::from_offset(text, 100)
+        );
+    }
+
+    #[test]
+    fn format_diagnostics() {
+        let warning = Diagnostic::warning("unknown language `pyhton`")
+            .in_file("docs/index.md")
+            .at(Position { line: 3, column: 1 });
+        assert_eq!(
+            "docs/index.md:3:1: warning: unknown language `pyhton`",
+            warning.to_string()
+        );
+        assert_eq!(
+            "docs/index.md: error: file not found",
+            Diagnostic::error("file not found")
+                .in_file("docs/index.md")
+                .to_string()
+        );
+        assert_eq!(
+            "warning: no pages",
+            Diagnostic::warning("no pages").to_string()
+        );
+    }
+
+    #[test]
+    fn collect_unique_diagnostics() {
+        let diagnostics = Diagnostics::default();
+        diagnostics.report(Diagnostic::warning("one").in_file("a.md"));
+        diagnostics.report(Diagnostic::warning("two").in_file("a.md"));
+        diagnostics.report(Diagnostic::warning("one").in_file("a.md"));
+        assert_eq!(2, diagnostics.take().len());
+        assert!(diagnostics.take().is_empty());
+    }
+}
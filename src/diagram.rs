@@ -0,0 +1,343 @@
+//! Diagram Rendering
+//!
+//! This module intercepts fenced code blocks written in a diagram language and
+//! renders them to inline SVG at build time. No external binaries or
+//! Javascript are needed to view the result. Code blocks in a language that no
+//! renderer understands are left for the highlighter.
+//!
+//! If the `svgbob-diagrams` feature is enabled then ASCII-art blocks fenced as
+//! `svgbob` or `bob` are rendered with the svgbob crate. Other languages, such
+//! as `mermaid` or `dot`, can be handed to an external command configured in
+//! the `[diagrams]` table of `docket.toml`. The command reads the diagram
+//! source on stdin and writes SVG to stdout. Command output is cached by the
+//! command and diagram source. The cache can outlive a single build, so
+//! unchanged diagrams aren't rendered again when watching for changes.
+//!
+//! Well-known diagram languages with no configured renderer are emitted as
+//! marked-up source, so a script added to the site can draw them.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{self, Write},
+    process::{Command, Stdio},
+    rc::Rc,
+    thread,
+};
+
+use log::debug;
+
+use crate::{config::Config, utils};
+
+/// Diagram languages which can be rendered in the browser if no command is
+/// configured for them.
+const CLIENT_SIDE_LANGUAGES: &[&str] = &["mermaid", "dot", "graphviz"];
+
+/// Diagram Renderer Trait
+///
+/// Implementations convert the source of a diagram in one or more languages
+/// to SVG markup.
+pub(crate) trait DiagramRenderer: Sync {
+    /// # Check Language Support
+    ///
+    /// Returns true if this renderer can draw diagrams in the given language.
+    fn supports(&self, lang: &str) -> bool;
+
+    /// # Render a Diagram
+    ///
+    /// Returns the SVG markup for the diagram, or a message describing why
+    /// the diagram could not be rendered. Renderers should style the SVG with
+    /// the site's colour variables so that it follows the light and dark
+    /// themes.
+    fn render(&self, lang: &str, source: &str) -> Result<String, String>;
+}
+
+#[cfg(feature = "svgbob-diagrams")]
+pub use svgbob_diagram::SvgbobRenderer;
+
+#[cfg(feature = "svgbob-diagrams")]
+mod svgbob_diagram {
+    use svgbob::Settings;
+
+    use super::DiagramRenderer;
+
+    /// Renders ASCII-art diagrams with svgbob.
+    pub struct SvgbobRenderer;
+
+    impl DiagramRenderer for SvgbobRenderer {
+        fn supports(&self, lang: &str) -> bool {
+            matches!(lang, "svgbob" | "bob")
+        }
+
+        fn render(&self, _lang: &str, source: &str) -> Result<String, String> {
+            let settings = Settings {
+                font_family: String::from("var(--fnt-family-mono)"),
+                fill_color: String::from("var(--col-fg)"),
+                stroke_color: String::from("var(--col-fg)"),
+                background: String::from("var(--col-bg)"),
+                include_backdrop: false,
+                ..Default::default()
+            };
+            Ok(svgbob::to_svg_with_settings(source, &settings))
+        }
+    }
+}
+
+/// External Command Renderer
+///
+/// Renders diagrams by piping the source through a local command, such as
+/// `dot -Tsvg`. The command line is split on whitespace; no shell is involved.
+pub(crate) struct CommandRenderer<'a> {
+    /// The language handled by this command
+    lang: &'a str,
+    /// The command line to run
+    command: &'a str,
+}
+
+impl<'a> CommandRenderer<'a> {
+    /// Create a Renderer for the given `lang`, using `command`
+    pub fn new(lang: &'a str, command: &'a str) -> Self {
+        CommandRenderer { lang, command }
+    }
+
+    /// Run the command, returning its raw output
+    fn run(&self, source: &str) -> io::Result<String> {
+        let mut args = self.command.split_whitespace();
+        let program = args
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // Feed stdin from another thread so a chatty renderer can't deadlock
+        // us by filling its stdout pipe before it has read all the input.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let source = source.to_owned();
+        let writer = thread::spawn(move || stdin.write_all(source.as_bytes()));
+
+        let output = child.wait_with_output()?;
+        writer
+            .join()
+            .map_err(|_| io::Error::other("stdin writer panicked"))??;
+
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "{} ({})",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl<'a> DiagramRenderer for CommandRenderer<'a> {
+    fn supports(&self, lang: &str) -> bool {
+        lang == self.lang
+    }
+
+    fn render(&self, lang: &str, source: &str) -> Result<String, String> {
+        let output = self.run(source).map_err(|e| {
+            format!(
+                "could not render `{}` diagram with `{}`: {}",
+                lang, self.command, e
+            )
+        })?;
+        // Drop any XML prolog or doctype so the SVG can be inlined.
+        match output.find("<svg") {
+            Some(start) => Ok(output[start..].trim_end().to_owned()),
+            None => Err(format!(
+                "`{}` didn't output an SVG for a `{}` diagram",
+                self.command, lang
+            )),
+        }
+    }
+}
+
+/// Diagram Cache
+///
+/// Holds the SVG written by diagram commands, keyed by the command and the
+/// full source of the diagram. Clones share the same cache, so one can be
+/// kept and given to each build to avoid rendering unchanged diagrams again.
+#[derive(Debug, Default, Clone)]
+pub struct DiagramCache(Rc<RefCell<HashMap<(String, String), String>>>);
+
+impl DiagramCache {
+    /// Create an Empty Diagram Cache
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get the SVG for the diagram `source` drawn by `command`, calling
+    /// `render` to draw it if it isn't in the cache. Failures aren't cached.
+    fn get_or_render<F>(&self, command: &str, source: &str, render: F) -> Result<String, String>
+    where
+        F: FnOnce() -> Result<String, String>,
+    {
+        let key = (command.to_owned(), source.to_owned());
+        if let Some(svg) = self.0.borrow().get(&key) {
+            debug!("Using cached diagram from `{}`", command);
+            return Ok(svg.clone());
+        }
+        let svg = render()?;
+        self.0.borrow_mut().insert(key, svg.clone());
+        Ok(svg)
+    }
+}
+
+/// # Get the Diagram Renderers
+///
+/// Returns the list of built-in renderers, in order of preference.
+fn get_renderers() -> &'static [&'static dyn DiagramRenderer] {
+    #[cfg(feature = "svgbob-diagrams")]
+    {
+        static RENDERERS: [&dyn DiagramRenderer; 1] = [&SvgbobRenderer];
+        &RENDERERS
+    }
+    #[cfg(not(feature = "svgbob-diagrams"))]
+    {
+        &[]
+    }
+}
+
+/// # Render a Diagram Block
+///
+/// Looks for a renderer for the code block's language `name` and renders the
+/// block. Built-in renderers are tried first, then any command configured for
+/// the language, with its output kept in the `cache`. Returns the HTML to
+/// emit in place of the code block, or `None` if the block isn't a diagram we
+/// can draw. If drawing the diagram fails the error message is returned.
+pub(crate) fn render_diagram(
+    config: &Config,
+    cache: &DiagramCache,
+    name: &str,
+    block: &str,
+) -> Option<Result<String, String>> {
+    let lang = name.split_whitespace().next()?;
+    let rendered = match get_renderers().iter().find(|r| r.supports(lang)) {
+        Some(renderer) => renderer.render(lang, block),
+        None => match config.diagrams.get_key_value(lang) {
+            Some((lang, command)) => cache.get_or_render(command, block, || {
+                CommandRenderer::new(lang, command).render(lang, block)
+            }),
+            None => return client_side_hook(lang, block).map(Ok),
+        },
+    };
+    Some(rendered.map(|svg| {
+        format!(
+            "<figure class=\"diagram diagram-{}\">{}</figure>\n",
+            utils::escape_html(lang),
+            svg
+        )
+    }))
+}
+
+/// # Client-side Rendering Hook
+///
+/// For well-known diagram languages with no renderer configured we emit the
+/// source in a marked-up block. Without Javascript this reads as a plain code
+/// block. Docket doesn't draw these itself, but a script added to the site
+/// can find the `data-diagram` attribute and draw them.
+fn client_side_hook(lang: &str, block: &str) -> Option<String> {
+    if !CLIENT_SIDE_LANGUAGES.contains(&lang) {
+        return None;
+    }
+    Some(format!(
+        "<pre class=\"diagram-source {lang}\" data-diagram=\"{lang}\"><code>{source}</code></pre>\n",
+        lang = utils::escape_html(lang),
+        source = utils::escape_html(block),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(config: &Config, name: &str, block: &str) -> Option<Result<String, String>> {
+        render_diagram(config, &DiagramCache::default(), name, block)
+    }
+
+    #[test]
+    fn unknown_language_is_not_a_diagram() {
+        let config = Config::default();
+        assert_eq!(None, render(&config, "rust", "fn main() {}"));
+        assert_eq!(None, render(&config, "", "+--+"));
+    }
+
+    #[test]
+    fn unconfigured_language_emits_client_hook() {
+        let config = Config::default();
+        let html = render(&config, "mermaid", "graph TD; A-->B")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            "<pre class=\"diagram-source mermaid\" data-diagram=\"mermaid\"><code>graph TD; A--&gt;B</code></pre>\n",
+            html
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn configured_command_renders_diagram() {
+        let mut config = Config::default();
+        config.diagrams.insert("dot".into(), "cat".into());
+        let html = render(&config, "dot", "<?xml version=\"1.0\"?>\n<svg></svg>\n");
+        assert_eq!(
+            Some(Ok(
+                "<figure class=\"diagram diagram-dot\"><svg></svg></figure>\n"
+            )),
+            html.as_ref().map(|html| html.as_deref())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_command_is_reported() {
+        let mut config = Config::default();
+        config.diagrams.insert("dot".into(), "false".into());
+        match render(&config, "dot", "digraph { a -> b }") {
+            Some(Err(message)) => assert!(message.starts_with("could not render `dot` diagram")),
+            other => panic!("Expected the diagram to fail, got {:?}", other),
+        }
+        config.diagrams.insert("dot".into(), "echo".into());
+        assert!(matches!(render(&config, "dot", "a"), Some(Err(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cache_command_output_by_source() {
+        let mut config = Config::default();
+        config.diagrams.insert("a\"b".into(), "cat".into());
+        let cache = DiagramCache::default();
+        let html = render_diagram(&config, &cache, "a\"b", "<svg>1</svg>").unwrap();
+        assert_eq!(
+            Ok("<figure class=\"diagram diagram-a&quot;b\"><svg>1</svg></figure>\n"),
+            html.as_deref()
+        );
+        render_diagram(&config, &cache, "a\"b", "<svg>2</svg>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(2, cache.0.borrow().len());
+
+        // Cached output is reused without running the command again.
+        assert_eq!(
+            Ok(String::from("<svg>1</svg>")),
+            cache.get_or_render("cat", "<svg>1</svg>", || unreachable!())
+        );
+    }
+
+    #[cfg(feature = "svgbob-diagrams")]
+    #[test]
+    fn svgbob_renders_inline_svg() {
+        let html = render(&Config::default(), "svgbob", "+--+\n|  |-->\n+--+")
+            .unwrap()
+            .unwrap();
+        assert!(html.starts_with("<figure class=\"diagram diagram-svgbob\"><svg"));
+        assert!(html.contains("var(--col-fg)"));
+        assert!(html.trim_end().ends_with("</figure>"));
+    }
+}
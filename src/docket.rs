@@ -1,14 +1,10 @@
-use std::{
-    fs, io,
-    path::{self, Path},
-};
-
-use log::trace;
+use std::path::{self, Path};
 
 use crate::{
-    doctree::{self, Bale},
+    diagnostics::Diagnostic,
     error::{Error, Result as DocketResult},
-    render,
+    render::{self, RenderContext, RenderContextBuilder},
+    source::{DiskFs, Source, SourceFs},
 };
 
 /// Docket
@@ -18,7 +14,7 @@ use crate::{
 #[derive(Debug)]
 pub struct Docket {
     title: String,
-    doctree_root: Bale,
+    source: Source,
 }
 
 impl Docket {
@@ -27,44 +23,83 @@ impl Docket {
     /// Once opned a documentaiton collection has a title, and can be rendreed
     /// to a target path.
     pub fn open<P: AsRef<Path>>(path: P) -> DocketResult<Self> {
-        if !path.as_ref().is_dir() {
-            Err(Error::SourcePathNotADirectory(path.as_ref().into()))?;
+        let path = path.as_ref();
+        if !path.is_dir() {
+            Err(Error::SourcePathNotADirectory(path.into()))?;
+        }
+        // Canonicalise the path so that directories such as `.` have a name.
+        let default_title = title_from_dir(&Path::canonicalize(path)?);
+        Self::open_source(path, Box::new(DiskFs), default_title)
+    }
+
+    /// Open a documentation collection from a custom file system
+    ///
+    /// Reads the documentation at `path` within the given file system `fs`,
+    /// rather than from the local disk.
+    pub fn open_with<P, F>(path: P, fs: F) -> DocketResult<Self>
+    where
+        P: AsRef<Path>,
+        F: SourceFs + 'static,
+    {
+        let path = path.as_ref();
+        if !fs.is_dir(path) {
+            Err(Error::SourcePathNotADirectory(path.into()))?;
         }
+        Self::open_source(path, Box::new(fs), title_from_dir(path))
+    }
+
+    /// Open the Source and Title for a Documentation Collection
+    ///
+    /// The title is read from the `title` file, if there is one. Otherwise the
+    /// `default_title` is used.
+    fn open_source(
+        path: &Path,
+        fs: Box<dyn SourceFs>,
+        default_title: String,
+    ) -> DocketResult<Self> {
+        let title_file = path.join("title");
+        let title = if fs.is_file(&title_file) {
+            fs.read_to_string(&title_file)?
+        } else {
+            default_title
+        };
         Ok(Docket {
-            title: title_from_path(path.as_ref())?,
-            doctree_root: doctree::open(&path)?,
+            title,
+            source: Source::open(path, fs)?,
         })
     }
 
+    /// Get the title of the documentation set
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
     /// Render to HTML
     ///
     /// Renders the documentation set. Creates a tree of HTML files into the
-    /// given `target` directory.
-    pub fn render<P: AsRef<Path>>(self, target: P) -> DocketResult<()> {
-        trace!(
-            "Rendering documentation for {} to {:?}",
-            self.title,
-            target.as_ref()
-        );
-        render::render(target, self.title, self.doctree_root)?;
-        Ok(())
+    /// given `target` directory. Returns any warnings found while rendering.
+    pub fn render<P: AsRef<Path>>(self, target: P) -> DocketResult<Vec<Diagnostic>> {
+        self.render_with(RenderContext::builder(target.as_ref()))
+    }
+
+    /// Render with a Custom Context
+    ///
+    /// Renders the documentation set using the settings from the given
+    /// `builder`, such as a custom layout or highlighter. Returns any warnings
+    /// found while rendering.
+    pub fn render_with(self, builder: RenderContextBuilder) -> DocketResult<Vec<Diagnostic>> {
+        render::render(builder.build(self.title, self.source)?)
     }
 }
 
-/// Calculate the title of the documentation set from the given path.
-fn title_from_path(path: &Path) -> std::result::Result<String, io::Error> {
-    let title_file = path.join("title");
-    Ok(if title_file.is_file() {
-        fs::read_to_string(title_file)?
-    } else {
-        Path::canonicalize(path)?
-            .components()
-            .filter_map(|c| match c {
-                path::Component::Normal(path) => path.to_owned().into_string().ok(),
-                _ => None,
-            })
-            .filter(|s| s != "docs")
-            .last()
-            .unwrap_or_else(|| String::from("Documentation"))
-    })
+/// Calculate the title of the documentation set from the given directory path.
+fn title_from_dir(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            path::Component::Normal(path) => path.to_owned().into_string().ok(),
+            _ => None,
+        })
+        .filter(|s| s != "docs")
+        .last()
+        .unwrap_or_else(|| String::from("Documentation"))
 }
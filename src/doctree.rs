@@ -1,17 +1,17 @@
 //! The Documentation Tree
 //!
 //! This module defines the types that model out the tree of documentation to be
-//! rendered. The tree is defined in such a way that it can be lazily traversed
-//! to avoid pulling the whole thing into memory at the beginning of rendering.
+//! rendered. Opening the tree reads and parses every page once, so that the
+//! navigation for the whole site can be built before any of it is rendered.
 //!
 //! The documentation tree is made up of two things: bales and pages. Bales form
 //! the interior nodes of the tree, and pages the leaves.
 
 use std::{
     borrow::Borrow,
-    fs,
+    collections::HashMap,
+    fmt::Write,
     path::{Path, PathBuf},
-    result,
     time::SystemTime,
 };
 
@@ -19,12 +19,21 @@ use log::info;
 
 use crate::{
     asset::Asset,
-    error::Result,
+    config::{self, Config, SlugConfig},
+    diagnostics::{Diagnostic, Position},
+    diagram::DiagramCache,
+    error::{Error, Result},
+    frontmatter::{self, FrontMatter},
+    highlight::Highlighter,
     search,
-    toc::Toc,
-    utils::{self, slugify_path},
+    source::Source,
+    toc::{Nodes, Toc, TocElement},
+    translation, utils,
 };
 
+/// The longest description taken from a page's first paragraph, in characters
+const MAX_DESCRIPTION_LEN: usize = 160;
+
 /// A Doctree Item
 ///
 /// Represents the kinds of item that can appear within the doctree.
@@ -32,8 +41,8 @@ pub(crate) enum DoctreeItem {
     /// A leaf page
     Page(Page),
 
-    /// An unopened bale
-    Bale(Bale),
+    /// A nested bale, already broken open
+    Bale(OpenBale),
 }
 
 /// A Documentation Page
@@ -43,11 +52,18 @@ pub(crate) enum DoctreeItem {
 /// TOC is a heirachical represenattion of the contents of the page. A TOC can
 /// be traversed to inspect the structure of the page; and rendered to HTML.
 #[derive(Debug)]
-pub(crate) struct Page {
+pub struct Page {
     slug: String,
     title: String,
     tree: Toc,
     modified: Option<SystemTime>,
+    authors: Vec<String>,
+    source_path: Option<PathBuf>,
+    aliases: Vec<String>,
+    description: Option<String>,
+    date: Option<SystemTime>,
+    feed: bool,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl search::SearchableDocument for Page {
@@ -70,12 +86,32 @@ impl search::SearchableDocument for Page {
 impl Page {
     /// Open a Page
     ///
-    /// Loads the contents of the given file and parses it as markdown.
-    pub fn open<P: AsRef<Path>>(path: P) -> result::Result<Self, std::io::Error> {
-        let modified = fs::metadata(&path).ok().and_then(|m| m.modified().ok());
-        let markdown = fs::read_to_string(&path)?;
-        let mut page = Self::from_parts(path, markdown);
-        page.modified = modified;
+    /// Loads the contents of the given file and parses it as markdown. Any
+    /// problems found with the page are reported to the `source`.
+    pub(crate) fn open<P: AsRef<Path>>(path: P, source: &Source) -> Result<Self> {
+        let markdown = source
+            .fs()
+            .read_to_string(path.as_ref())
+            .map_err(|e| Error::in_file(path.as_ref(), e))?;
+        let mut page = Self::from_parts(
+            &path,
+            markdown,
+            source.config(),
+            source.highlighter(),
+            source.diagrams(),
+        );
+        for diagnostic in page.diagnostics.drain(..) {
+            source.report(diagnostic);
+        }
+        page.modified = source.modified(path.as_ref());
+        if page.authors.is_empty() {
+            page.authors = source.authors(path.as_ref()).to_vec();
+        }
+        page.source_path = Some(source.relative_path(path.as_ref()).to_owned());
+        // Translations share the slug of the page they translate.
+        if let Some((original, _)) = translation::split_translation(source, path.as_ref()) {
+            page.slug = source.config().slugs.slugify_path(original);
+        }
         Ok(page)
     }
 
@@ -84,34 +120,94 @@ impl Page {
     /// Creates a page whose content is provided directly as a markdown string
     /// rather than read from disk. Used to generate auto-index pages for bales
     /// that have no `index.md`.
-    pub fn synthetic(title: String, markdown: String) -> Self {
-        let tree = Toc::new(&markdown);
+    pub(crate) fn synthetic(
+        title: String,
+        markdown: String,
+        config: &Config,
+        highlighter: &dyn Highlighter,
+    ) -> Self {
+        // Synthetic pages are generated by docket, so have no diagrams.
+        let tree = Toc::new(&markdown, config, highlighter, &DiagramCache::default());
         Page {
             slug: String::from("index"),
             title,
             tree,
             modified: None,
+            authors: Vec::new(),
+            source_path: None,
+            aliases: Vec::new(),
+            description: None,
+            date: None,
+            feed: false,
+            diagnostics: Vec::new(),
         }
     }
 
     /// Construct a Page from Constituent Parts
     ///
-    /// Builds the TOC tree for the given page, and returns the opened and
-    /// parsed page.
-    fn from_parts<P: AsRef<Path>, M: Borrow<str>>(path: P, markdown: M) -> Self {
-        let slug = utils::slugify_path(&path);
-        let tree = Toc::new(markdown.borrow());
-        let title = tree.primary_heading().cloned().unwrap_or_else(|| {
-            path.as_ref()
-                .file_stem()
-                .unwrap()
-                .to_string_lossy()
-                .into_owned()
-        });
-        Page { slug, title, tree, modified: None }
+    /// Splits off any front matter, builds the TOC tree for the given page,
+    /// and returns the opened and parsed page.
+    fn from_parts<P: AsRef<Path>, M: Borrow<str>>(
+        path: P,
+        markdown: M,
+        config: &Config,
+        highlighter: &dyn Highlighter,
+        diagrams: &DiagramCache,
+    ) -> Self {
+        let path = path.as_ref();
+        let slug = config.slugs.slugify_path(path);
+        let (front, markdown, front_lines) = frontmatter::split(markdown.borrow());
+        let mut tree = Toc::new(markdown, config, highlighter, diagrams);
+        // Positions within the markdown are after the front matter.
+        let mut diagnostics: Vec<_> = tree
+            .take_diagnostics()
+            .into_iter()
+            .map(|diagnostic| match diagnostic.position() {
+                Some(Position { line, column }) => diagnostic.at(Position {
+                    line: line + front_lines,
+                    column,
+                }),
+                None => diagnostic,
+            })
+            .map(|diagnostic| diagnostic.in_file(path))
+            .collect();
+        let front = match front.map(FrontMatter::parse).transpose() {
+            Ok(front) => front.unwrap_or_default(),
+            Err(err) => {
+                diagnostics.push(
+                    Diagnostic::warning(format!("invalid front matter: {}", err.message()))
+                        .in_file(path)
+                        .at(Position { line: 2, column: 1 }),
+                );
+                FrontMatter::default()
+            }
+        };
+        let title = match tree.primary_heading() {
+            Some(heading) => heading.clone(),
+            None => {
+                diagnostics.push(
+                    Diagnostic::warning("page has no heading, using the file name as its title")
+                        .in_file(path),
+                );
+                utils::escape_html(path.file_stem().unwrap().to_string_lossy())
+            }
+        };
+        Page {
+            slug,
+            title,
+            tree,
+            modified: None,
+            source_path: None,
+            date: front.date_time(),
+            authors: front.authors,
+            feed: front.feed,
+            aliases: front.aliases,
+            description: front.description,
+            diagnostics,
+        }
     }
 
-    /// Get the title for this page
+    /// Get the title for this page, as HTML
     pub fn title(&self) -> &str {
         &self.title
     }
@@ -122,7 +218,7 @@ impl Page {
     }
 
     /// Get the content
-    pub fn content(&self) -> &Toc {
+    pub(crate) fn content(&self) -> &Toc {
         &self.tree
     }
 
@@ -131,6 +227,89 @@ impl Page {
         self.modified
     }
 
+    /// Get the date the page was published
+    ///
+    /// Uses the `date` from the page's front matter if there is one, otherwise
+    /// the time the page was last modified.
+    pub fn date(&self) -> Option<SystemTime> {
+        self.date.or(self.modified)
+    }
+
+    /// Get the authors of this page from its front matter, or otherwise its
+    /// contributors, if known
+    pub fn authors(&self) -> &[String] {
+        &self.authors
+    }
+
+    /// Get the path of this page's source file, relative to the root of the
+    /// documentation. Synthetic pages have no source file.
+    pub fn source_path(&self) -> Option<&Path> {
+        self.source_path.as_deref()
+    }
+
+    /// Get the paths which redirect to this page, as given in its front matter
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Get a Short Description of the Page
+    ///
+    /// Uses the `description` from the page's front matter if there is one,
+    /// otherwise the text of the page's first paragraph, shortened if needed.
+    pub fn description(&self) -> Option<String> {
+        if let Some(description) = &self.description {
+            return Some(description.clone());
+        }
+        // The first block of HTML may hold more than one paragraph.
+        let html = self.first_paragraph()?;
+        let text = utils::html_to_text(html.split("</p>").next().unwrap_or(html));
+        if text.is_empty() {
+            None
+        } else {
+            Some(utils::truncate_words(&text, MAX_DESCRIPTION_LEN))
+        }
+    }
+
+    /// Render the Page's Content as HTML
+    ///
+    /// Headings are given `id`s so they can be linked to, and `[TOC]` markers
+    /// are replaced with a nested list of links to the page's headings.
+    pub fn content_html(&self) -> String {
+        fn write_nodes(out: &mut String, nodes: Nodes) {
+            out.push_str("<ul>");
+            for node in nodes {
+                let _ = write!(
+                    out,
+                    "<li><a href='#{}'>{}</a>",
+                    node.heading.slug, node.heading.contents
+                );
+                if node.nodes().next().is_some() {
+                    write_nodes(out, node.nodes());
+                }
+                out.push_str("</li>");
+            }
+            out.push_str("</ul>");
+        }
+
+        let mut out = String::new();
+        for element in self.content().walk_elements() {
+            match element {
+                TocElement::Html(html) => out.push_str(html),
+                TocElement::TocReference => write_nodes(&mut out, self.content().nodes()),
+                TocElement::Node(node) => {
+                    let _ = write!(
+                        out,
+                        "<{level} id='{slug}'>{contents}</{level}>",
+                        level = node.heading.level,
+                        slug = node.heading.slug,
+                        contents = node.heading.contents,
+                    );
+                }
+            }
+        }
+        out
+    }
+
     /// Get the First Paragraph
     ///
     /// Walks the element tree and returns the HTML of the first non-empty HTML
@@ -138,7 +317,7 @@ impl Page {
     /// if the page contains no prose content.
     pub fn first_paragraph(&self) -> Option<&str> {
         for el in self.content().walk_elements() {
-            if let crate::toc::TocElement::Html(html) = el {
+            if let TocElement::Html(html) = el {
                 let trimmed = html.trim();
                 if !trimmed.is_empty() {
                     return Some(html);
@@ -171,6 +350,8 @@ pub(crate) struct Bale {
     assets: Vec<PathBuf>,
     /// The paths we susepct to be child bales
     nested: Vec<PathBuf>,
+    /// The pages left out because they haven't been translated
+    untranslated: Vec<PathBuf>,
 }
 
 impl Bale {
@@ -179,32 +360,51 @@ impl Bale {
     /// Wraps the given `path` as a bale. This performs a shallow traversal of
     /// the directory to find the index to produce the `Frontispiece`. The full
     /// contents of the bale can be retrieved by `Bale::break_open`.
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+    pub fn new<P: AsRef<Path>>(path: P, source: &Source) -> Result<Self> {
         let mut index = None;
+        let mut not_found = None;
         let mut footer = None;
+        let is_root = translation::tree_path(source, path.as_ref())
+            .as_os_str()
+            .is_empty();
         let mut pages = Vec::new();
         let mut assets = Vec::new();
         let mut nested = Vec::new();
 
         // Walk the items in the directory and collect them into the initial
         // unsorted bale contents. We're just using raw paths at this point to refer
-        // to all the bale's contents.
-        for entry in fs::read_dir(&path)? {
-            let entry = entry?;
-            let path = entry.path().clone();
-
-            if path.is_file() {
+        // to all the bale's contents. Translations are swapped in as we go.
+        let fs = source.fs();
+        let listing = translation::read_dir(source, path.as_ref())
+            .map_err(|e| Error::in_file(path.as_ref(), e))?;
+        for path in listing.entries {
+            if source.is_excluded(&path) {
+                continue;
+            }
+            if fs.is_file(&path) {
+                // The site's settings aren't part of the output.
+                if path.file_name() == Some(config::CONFIG_FILE_NAME.as_ref())
+                    || path == source.root().join(source.config().strings_file())
+                {
+                    continue;
+                }
                 match utils::normalised_path_ext(&path).as_deref() {
                     Some("md" | "markdown" | "mdown") => {
-                        match utils::normalised_stem(&path).as_deref() {
+                        match translation::normalised_stem(source, &path).as_deref() {
                             Some("index" | "readme") => index = Some(path),
-                            Some("footer") => footer = Some(fs::read_to_string(path)?),
+                            Some("404") if is_root => not_found = Some(path),
+                            Some("footer") => {
+                                let contents = fs
+                                    .read_to_string(&path)
+                                    .map_err(|e| Error::in_file(&path, e))?;
+                                footer = Some(contents)
+                            }
                             _ => pages.push(path),
                         }
                     }
                     _ => assets.push(path),
                 }
-            } else {
+            } else if path.file_name() != Some(config::TEMPLATES_DIR_NAME.as_ref()) {
                 nested.push(path);
             }
         }
@@ -212,96 +412,191 @@ impl Bale {
         // If we have an index item in this bale then open the page. We need this to
         // know the bale's intended title for navigation purposes.
         let index = match index {
-            Some(path) => Some(Page::open(path)?),
+            Some(path) => Some(Page::open(path, source)?),
+            None => None,
+        };
+        let not_found = match not_found {
+            Some(path) => Some(Box::new(Page::open(path, source)?)),
             None => None,
         };
 
+        // Bales can be marked as feeds in their index's front matter, or in the
+        // site configuration.
+        let feed = index.as_ref().is_some_and(|page| page.feed)
+            || source
+                .config()
+                .feeds
+                .iter()
+                .any(|feed| feed == translation::tree_path(source, path.as_ref()));
+        let mut frontispiece = Frontispiece::new(&path, index, footer, &source.config().slugs);
+        frontispiece.feed = feed;
+        frontispiece.not_found = not_found;
+
         Ok(Bale {
-            frontispiece: Frontispiece::new(path, index, footer),
+            frontispiece,
             pages,
             assets,
             nested,
+            untranslated: listing.untranslated,
         })
     }
 
     /// Break Open the Bale
     ///
     /// This reifies the contents of the bale. Inner items are converted into
-    /// real pages and bales.
-    pub fn break_open(self) -> Result<(Frontispiece, Vec<Asset>, Vec<DoctreeItem>)> {
+    /// real pages and bales, with nested bales broken open in turn.
+    pub fn break_open(self, source: &Source) -> Result<OpenBale> {
         info!(
             "Breaking open bale {} ({})",
             self.frontispiece.title,
             self.frontispiece.slug(),
         );
 
+        report_untranslated(source, &self.untranslated);
         let mut assets: Vec<_> = self.assets.into_iter().map(Asset::path).collect();
         let mut items = Vec::with_capacity(self.pages.len() + self.nested.len());
 
         for page in self.pages {
-            items.push((
-                utils::normalised_stem(&page),
-                DoctreeItem::Page(Page::open(page)?),
-            ));
+            let item = DoctreeItem::Page(Page::open(&page, source)?);
+            items.push((translation::normalised_stem(source, &page), page, item));
         }
 
         for nested in self.nested {
-            let bale = Bale::new(&nested)?;
-            if bale.frontispiece.index.is_none() && bale.pages.is_empty() {
+            let bale = Bale::new(&nested, source)?;
+            if bale.is_empty() && !bale.untranslated.is_empty() {
+                // None of the bale's pages have been translated yet.
+                report_untranslated(source, &bale.untranslated);
+            } else if bale.is_empty() {
                 info!(
                     "Inner item {:?} does not appear to be able. Adding as an asset",
                     &nested
                 );
+                // Directories of images and the like are expected. Markdown
+                // deeper inside would be copied as-is rather than rendered.
+                if contains_markdown(source, &nested) {
+                    source.report(
+                        Diagnostic::warning(
+                            "directory has no index or pages, so its markdown is copied as an asset",
+                        )
+                        .in_file(&nested),
+                    );
+                }
                 assets.push(Asset::path(nested));
             } else {
-                items.push((utils::normalised_stem(&nested), DoctreeItem::Bale(bale)));
+                let stem = translation::normalised_stem(source, &nested);
+                items.push((stem, nested, DoctreeItem::Bale(bale.break_open(source)?)));
             }
         }
 
         // Sort the items by their origional path. This allows files on disk to
         // be given a prefix that is stripped off in slugification but still
         // affects the item's order within the documentation tree.
-        items.sort_by_cached_key(|(k, _)| k.clone());
+        items.sort_by_cached_key(|(k, _, _)| k.clone());
+
+        // Items with the same slug would be rendered to the same place.
+        let mut seen = HashMap::new();
+        for (_, path, item) in &items {
+            let slug = match item {
+                DoctreeItem::Page(page) => page.slug(),
+                DoctreeItem::Bale(bale) => bale.frontispiece().slug(),
+            };
+            if let Some(first) = seen.insert(slug, path) {
+                source.report(
+                    Diagnostic::warning(format!(
+                        "duplicate slug `{}`, also used by {}",
+                        slug,
+                        first.display()
+                    ))
+                    .in_file(path),
+                );
+            }
+        }
 
-        Ok((
-            self.frontispiece,
+        Ok(OpenBale {
+            frontispiece: self.frontispiece,
             assets,
-            items.into_iter().map(|(_, i)| i).collect(),
-        ))
+            items: items.into_iter().map(|(_, _, i)| i).collect(),
+        })
     }
 
+    /// Check if this bale has neither an index nor any pages
+    fn is_empty(&self) -> bool {
+        self.frontispiece.index.is_none() && self.pages.is_empty()
+    }
+}
+
+/// An Opened Bale
+///
+/// The contents of a bale once it has been broken open: its frontispiece, its
+/// assets, and the pages and nested bales within it.
+pub(crate) struct OpenBale {
+    frontispiece: Frontispiece,
+    assets: Vec<Asset>,
+    items: Vec<DoctreeItem>,
+}
+
+impl OpenBale {
     /// Get the Frontispiece for this bale
     pub(crate) fn frontispiece(&self) -> &Frontispiece {
         &self.frontispiece
     }
 
-    /// Peek at the children of this bale without fully opening it.
+    /// Split the bale into its frontispiece, assets, and inner items
+    pub(crate) fn into_parts(self) -> (Frontispiece, Vec<Asset>, Vec<DoctreeItem>) {
+        (self.frontispiece, self.assets, self.items)
+    }
+
+    /// Outline the Bale
     ///
-    /// Returns (slug, title) pairs for each child page and nested bale, in
-    /// the same sort order that `break_open` would produce.
-    pub(crate) fn peek_children(&self) -> Vec<(String, String)> {
-        let mut items: Vec<(&PathBuf, bool)> = self
-            .pages
+    /// Returns a summary of each child page and nested bale, in order.
+    pub(crate) fn outline(&self) -> Vec<OutlineItem> {
+        self.items
             .iter()
-            .map(|p| (p, false))
-            .chain(self.nested.iter().map(|p| (p, true)))
-            .collect();
-        items.sort_by_cached_key(|(p, _)| utils::normalised_stem(p));
-
-        let mut result = Vec::new();
-        for (path, is_nested) in items {
-            if is_nested {
-                if let Ok(bale) = Bale::new(path) {
-                    result.push((
-                        bale.frontispiece().slug().to_owned(),
-                        bale.frontispiece().title().to_owned(),
-                    ));
-                }
-            } else if let Ok(page) = Page::open(path) {
-                result.push((page.slug().to_owned(), page.title().to_owned()));
-            }
+            .map(|item| match item {
+                DoctreeItem::Page(page) => OutlineItem::Page {
+                    slug: page.slug().to_owned(),
+                    title: page.title().to_owned(),
+                },
+                DoctreeItem::Bale(bale) => OutlineItem::Bale {
+                    slug: bale.frontispiece().slug().to_owned(),
+                    title: bale.frontispiece().title().to_owned(),
+                    items: bale.outline(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Outline Item
+///
+/// A summary of an item within the doctree, used to build the site-wide
+/// navigation.
+#[derive(Debug)]
+pub(crate) enum OutlineItem {
+    /// A leaf page
+    Page { slug: String, title: String },
+
+    /// A nested bale, and the outline of its contents
+    Bale {
+        slug: String,
+        title: String,
+        items: Vec<OutlineItem>,
+    },
+}
+
+impl OutlineItem {
+    /// Get the item's slug
+    pub fn slug(&self) -> &str {
+        match self {
+            OutlineItem::Page { slug, .. } | OutlineItem::Bale { slug, .. } => slug,
+        }
+    }
+
+    /// Get the item's title
+    pub fn title(&self) -> &str {
+        match self {
+            OutlineItem::Page { title, .. } | OutlineItem::Bale { title, .. } => title,
         }
-        result
     }
 }
 
@@ -312,8 +607,8 @@ impl Bale {
 /// This type is used to group together the index.
 #[derive(Debug)]
 pub(crate) struct Frontispiece {
-    /// The title for this bale. This is from the index page, if there is one,
-    /// or falls back to the directory name otherwise.
+    /// The title for this bale, as HTML. This is from the index page, if
+    /// there is one, or falls back to the directory name otherwise.
     title: String,
 
     /// The slug for this bale
@@ -327,16 +622,30 @@ pub(crate) struct Frontispiece {
     /// The footer information for this bale. Rendering of any nested pages
     /// should use this as the markdown for the page's footer.
     footer: Option<String>,
+
+    /// Should a feed of the bale's pages be published
+    feed: bool,
+
+    /// The page shown for missing paths. Only the root bale has one.
+    not_found: Option<Box<Page>>,
 }
 
 impl Frontispiece {
     /// Create a new Frontispiece
     ///
-    /// This picks a title and slug for the bale based on the bale's path.
-    fn new<P: AsRef<Path>>(path: P, index: Option<Page>, footer: Option<String>) -> Frontispiece {
+    /// This picks a title and slug for the bale based on the bale's path. The
+    /// slug is made with the given `slugs` policy.
+    fn new<P: AsRef<Path>>(
+        path: P,
+        index: Option<Page>,
+        footer: Option<String>,
+        slugs: &SlugConfig,
+    ) -> Frontispiece {
         let title = match &index {
             Some(page) => page.title.clone(),
-            None => utils::prettify_dir(&path).expect("Could not create a title"),
+            None => {
+                utils::escape_html(utils::prettify_dir(&path).expect("Could not create a title"))
+            }
         };
         let footer = footer.map(|text| {
             let mut output = String::new();
@@ -345,9 +654,11 @@ impl Frontispiece {
         });
         Frontispiece {
             title,
-            slug: slugify_path(path),
+            slug: slugs.slugify_path(path),
             index,
             footer,
+            feed: false,
+            not_found: None,
         }
     }
 
@@ -370,27 +681,241 @@ impl Frontispiece {
     pub fn footer(&self) -> Option<&str> {
         self.footer.as_deref()
     }
+
+    /// Check if a feed of this bale's pages should be published
+    pub fn is_feed(&self) -> bool {
+        self.feed
+    }
+
+    /// Get the page to show for missing paths, if one was given
+    pub fn not_found_page(&self) -> Option<&Page> {
+        self.not_found.as_deref()
+    }
 }
 
 /// Open a Doctree
 ///
 /// This tries to create a new doctree rooted at the given `path`. If the path
-/// can be opened and loaded as a valid `Bale` then that `Bale` is returned. If
-/// there was an error initialising the doctree that failure is propagated.
-pub(crate) fn open<P: AsRef<Path>>(path: P) -> Result<Bale> {
-    Bale::new(path)
+/// can be opened and loaded as a valid `Bale` then it is broken open, along
+/// with every bale nested within it. If there was an error initialising the
+/// doctree that failure is propagated.
+pub(crate) fn open<P: AsRef<Path>>(path: P, source: &Source) -> Result<OpenBale> {
+    Bale::new(path, source)?.break_open(source)
+}
+
+/// Report each of the `pages` which is missing from the translation being read
+fn report_untranslated(source: &Source, pages: &[PathBuf]) {
+    if let Some(code) = source.translation() {
+        for page in pages {
+            source.report(
+                Diagnostic::warning(format!("missing `{}` translation", code)).in_file(page),
+            );
+        }
+    }
+}
+
+/// Check if the directory at `path`, or any directory within it, contains
+/// markdown files.
+fn contains_markdown(source: &Source, path: &Path) -> bool {
+    let fs = source.fs();
+    fs.read_dir(path)
+        .map(|entries| {
+            entries.iter().any(|entry| {
+                if fs.is_dir(entry) {
+                    contains_markdown(source, entry)
+                } else {
+                    matches!(
+                        utils::normalised_path_ext(entry).as_deref(),
+                        Some("md" | "markdown" | "mdown")
+                    )
+                }
+            })
+        })
+        .unwrap_or(false)
 }
 
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use crate::{highlight, source::MemoryFs};
     use std::path::PathBuf;
 
+    #[test]
+    fn open_doctree_from_memory() {
+        let fs: MemoryFs = [
+            ("docs/index.md", "# Home\n"),
+            ("docs/01-intro.md", "# Introduction\n"),
+            ("docs/footer.md", "Footer text"),
+            ("docs/guide/index.md", "# The Guide\n"),
+            ("docs/guide/setup.md", "# Setup\n"),
+            ("docs/img/logo.svg", "<svg/>"),
+        ]
+        .into_iter()
+        .collect();
+        let source = Source::open("docs", Box::new(fs)).unwrap();
+        let bale = open("docs", &source).unwrap();
+
+        assert_eq!("Home", bale.frontispiece().title());
+        assert_eq!(Some("<p>Footer text</p>\n"), bale.frontispiece().footer());
+
+        let outline = bale.outline();
+        let titles: Vec<_> = outline.iter().map(|item| item.title()).collect();
+        assert_eq!(vec!["Introduction", "The Guide"], titles);
+        match &outline[1] {
+            OutlineItem::Bale { items, .. } => {
+                assert_eq!(1, items.len());
+                assert_eq!("Setup", items[0].title());
+            }
+            item => panic!("Expected a bale, found {:?}", item),
+        }
+
+        let (_, assets, items) = bale.into_parts();
+        assert_eq!(1, assets.len());
+        assert_eq!(2, items.len());
+    }
+
+    #[test]
+    fn report_doctree_problems() {
+        let fs: MemoryFs = [
+            ("docs/index.md", "# Home\n"),
+            ("docs/01-intro.md", "# Introduction\n"),
+            ("docs/intro.md", "No heading here\n"),
+        ]
+        .into_iter()
+        .collect();
+        let source = Source::open("docs", Box::new(fs)).unwrap();
+        open("docs", &source).unwrap();
+
+        let messages: Vec<_> = source
+            .take_diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "docs/intro.md: warning: page has no heading, using the file name as its title",
+                "docs/intro.md: warning: duplicate slug `intro`, also used by docs/01-intro.md",
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn report_colliding_slugs() {
+        let fs: MemoryFs = [
+            ("docs/index.md", "# Home\n"),
+            ("docs/Café.md", "# Café\n"),
+            ("docs/cafe.md", "# Cafe\n"),
+            ("docs/guide.md", "# Guide\n"),
+            ("docs/Guide/index.md", "# Guide\n"),
+        ]
+        .into_iter()
+        .collect();
+        let source = Source::open("docs", Box::new(fs)).unwrap();
+        open("docs", &source).unwrap();
+
+        let messages: Vec<_> = source
+            .take_diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "docs/Café.md: warning: duplicate slug `cafe`, also used by docs/cafe.md",
+                "docs/Guide: warning: duplicate slug `guide`, also used by docs/guide.md",
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn page_with_front_matter() {
+        let config = Config::default();
+        let page = Page::from_parts(
+            "foo/bar.md",
+            "+++\naliases = [\"old-bar/\"]\n+++\n# Bar {#bar}\n\n## Baz {#bar}\n",
+            &config,
+            &highlight::PlainHighlighter,
+            &DiagramCache::default(),
+        );
+        assert_eq!("Bar", page.title());
+        assert_eq!(vec!["old-bar/"], page.aliases());
+        assert_eq!(
+            Some(Position { line: 6, column: 1 }),
+            page.diagnostics[0].position()
+        );
+
+        let page = Page::from_parts(
+            "foo/bar.md",
+            "+++\naliases = 1\n+++\n# Bar\n",
+            &config,
+            &highlight::PlainHighlighter,
+            &DiagramCache::default(),
+        );
+        assert!(page.aliases().is_empty());
+        assert!(page.diagnostics[0]
+            .message()
+            .starts_with("invalid front matter: "));
+    }
+
+    #[test]
+    fn page_descriptions() {
+        let config = Config::default();
+        let page = |markdown: &str| {
+            Page::from_parts(
+                "foo/bar.md",
+                markdown,
+                &config,
+                &highlight::PlainHighlighter,
+                &DiagramCache::default(),
+            )
+        };
+        assert_eq!(
+            Some(String::from("Some <text> with formatting.")),
+            page("# Title\n\nSome &lt;text&gt; *with*\nformatting.\n\nMore.\n").description()
+        );
+        assert_eq!(
+            Some(String::from("Given.")),
+            page("+++\ndescription = \"Given.\"\n+++\n# Title\n\nText.\n").description()
+        );
+        assert_eq!(None, page("# Title\n").description());
+    }
+
+    #[test]
+    fn bale_feed_from_front_matter() {
+        let fs: MemoryFs = [
+            ("docs/index.md", "# Home\n"),
+            ("docs/news/index.md", "+++\nfeed = true\n+++\n# News\n"),
+            ("docs/other/index.md", "# Other\n"),
+        ]
+        .into_iter()
+        .collect();
+        let source = Source::open("docs", Box::new(fs)).unwrap();
+        let news = open("docs/news", &source).unwrap();
+        assert!(news.frontispiece().is_feed());
+        let other = open("docs/other", &source).unwrap();
+        assert!(!other.frontispiece().is_feed());
+    }
+
+    #[test]
+    fn open_missing_page_reports_path() {
+        let source = Source::open("docs", Box::new(MemoryFs::new())).unwrap();
+        let err = Page::open("docs/missing.md", &source).unwrap_err();
+        assert!(err.to_string().starts_with("docs/missing.md: "));
+    }
+
     #[test]
     fn page_has_search_terms() {
         let path = PathBuf::from("foo/bar.md");
-        let page = Page::from_parts(&path, "Some sample text in some text");
+        let config = Config::default();
+        let page = Page::from_parts(
+            &path,
+            "Some sample text in some text",
+            &config,
+            highlight::get_hilighter(&config),
+            &DiagramCache::default(),
+        );
 
         let index = page.content().search_index().as_raw();
         assert_ne!(0, index.len());
@@ -401,9 +926,29 @@ mod test {
         assert!(some_fq > sample_fq);
     }
 
+    #[test]
+    fn page_content_as_html() {
+        let config = Config::default();
+        let page = Page::from_parts(
+            "foo/bar.md",
+            "# title\n\n[TOC]\n\n## first\n\nText\n",
+            &config,
+            highlight::get_hilighter(&config),
+            &DiagramCache::default(),
+        );
+
+        assert_eq!(
+            "<h1 id='title'>title</h1>\
+             <ul><li><a href='#title'>title</a><ul><li><a href='#first'>first</a></li></ul></li></ul>\
+             <h2 id='first'>first</h2><p>Text</p>\n",
+            page.content_html()
+        );
+    }
+
     #[test]
     fn index_of_example_markdown() {
         let path = PathBuf::from("foo/bar.md");
+        let config = Config::default();
         let page = Page::from_parts(
             &path,
             r###"
@@ -442,6 +987,9 @@ was, or Longitude either, but thought they were nice grand words to
 say.)
 
         "###,
+            &config,
+            highlight::get_hilighter(&config),
+            &DiagramCache::default(),
         );
 
         assert_eq!("Down the Rabbit Hole", page.title);
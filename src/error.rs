@@ -1,4 +1,6 @@
-use std::{io, path::PathBuf};
+use std::{fmt, io, path::PathBuf};
+
+use crate::diagnostics::Diagnostic;
 
 /// Docket Error Type
 ///
@@ -11,12 +13,35 @@ pub enum Error {
     SourcePathNotADirectory(PathBuf),
     /// A generic IO Error occured
     Io(io::Error),
+    /// The site configuration file could not be parsed
+    InvalidConfig(PathBuf, String),
+    /// A problem with one of the documentation's files
+    Diagnostic(Diagnostic),
+    /// The build had warnings, which are errors in strict mode
+    Strict(Vec<Diagnostic>),
+    /// The archive format could not be inferred from the output path
+    #[cfg(feature = "archive")]
+    UnknownArchiveFormat(PathBuf),
     /// Annotated inner error
     #[cfg(feature = "watch")]
     Annotated(String, Box<dyn std::error::Error>),
 }
 
 impl Error {
+    /// Create an Error for a File
+    ///
+    /// Records that `err` happened while processing the file at `path`.
+    pub(crate) fn in_file<P, E>(path: P, err: E) -> Self
+    where
+        P: Into<PathBuf>,
+        E: fmt::Display,
+    {
+        Error::Diagnostic(Diagnostic::error(err.to_string()).in_file(path))
+    }
+
+    /// Create an Annotated Error
+    ///
+    /// Wraps the given `err` with a description of what was being done.
     #[cfg(feature = "watch")]
     pub fn annotated<S, E>(anno: S, err: E) -> Self
     where
@@ -38,6 +63,32 @@ impl std::fmt::Display for Error {
                 )
             }
             Error::Io(io) => io.fmt(f),
+            Error::InvalidConfig(path, err) => {
+                write!(f, "Invalid configuration in {:?}: {}", path, err)
+            }
+            Error::Diagnostic(diagnostic) => {
+                diagnostic.fmt_location(f)?;
+                f.write_str(diagnostic.message())
+            }
+            Error::Strict(diagnostics) => {
+                write!(
+                    f,
+                    "Build failed with {} warning(s) in strict mode:",
+                    diagnostics.len()
+                )?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n  {}", diagnostic)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "archive")]
+            Error::UnknownArchiveFormat(path) => {
+                write!(
+                    f,
+                    "Unknown archive format for {:?}, expected a .zip or .tar",
+                    path
+                )
+            }
             #[cfg(feature = "watch")]
             Error::Annotated(anno, e) => {
                 write!(f, "{}: {}", anno, e)
@@ -65,19 +116,3 @@ impl From<io::Error> for Error {
 ///
 /// Shorthand result type for functions returning docket errors.
 pub type Result<T> = std::result::Result<T, Error>;
-
-#[cfg(feature = "watch")]
-pub(crate) trait ResultExt<T, A: Into<String>> {
-    fn annotate_err(self, anno: A) -> Result<T>;
-}
-
-#[cfg(feature = "watch")]
-impl<T, E, A> ResultExt<T, A> for std::result::Result<T, E>
-where
-    E: std::error::Error + 'static,
-    A: Into<String>,
-{
-    fn annotate_err(self, anno: A) -> Result<T> {
-        self.map_err(|err| Error::annotated(anno, err))
-    }
-}
@@ -0,0 +1,158 @@
+//! Atom Feeds
+//!
+//! A bale can be marked as a feed, such as for a `news/` bale of release
+//! notes. An Atom feed of the pages within the bale is then written alongside
+//! its index page as `feed.xml`, newest first, so readers can subscribe to it.
+//! Feeds need absolute links, so are only written when the site's `base-url`
+//! is configured.
+
+use std::{cmp::Reverse, io, path::Path, time::SystemTime};
+
+use crate::{output::OutputSink, sitemap, utils};
+
+/// The name of the feed file written to a bale's output directory
+pub(crate) const FEED_FILE_NAME: &str = "feed.xml";
+
+/// An Entry in a Feed
+#[derive(Debug)]
+pub(crate) struct FeedEntry {
+    /// The title of the page, as plain text
+    pub title: String,
+    /// Path to the page from the site root (e.g. `news/v1-0/`)
+    pub path: String,
+    /// When the page was published, if known
+    pub date: Option<SystemTime>,
+    /// The people who wrote the page, if known
+    pub authors: Vec<String>,
+    /// A short summary of the page
+    pub summary: Option<String>,
+}
+
+/// Write a Feed
+///
+/// Writes the feed for the bale at `path` from the site root, with the given
+/// `title` and `author`, listing each of the `entries` newest first. Entries
+/// without a date are given the time of the `build`.
+pub(crate) fn write_feed(
+    output: &dyn OutputSink,
+    base_url: &str,
+    path: &str,
+    title: &str,
+    author: &str,
+    build: SystemTime,
+    mut entries: Vec<FeedEntry>,
+) -> io::Result<()> {
+    entries.sort_by_key(|entry| Reverse(entry.date.unwrap_or(build)));
+    let updated = entries
+        .iter()
+        .map(|entry| entry.date.unwrap_or(build))
+        .max()
+        .unwrap_or(build);
+    let timestamp = |date: Option<SystemTime>| {
+        utils::format_timestamp(date.unwrap_or(build)).unwrap_or_default()
+    };
+    let authors = |names: &[String]| -> String {
+        names
+            .iter()
+            .map(|name| format!("<author><name>{}</name></author>", utils::escape_html(name)))
+            .collect()
+    };
+
+    let feed_url = format!(
+        "{}{}",
+        sitemap::absolute_url(base_url, path),
+        FEED_FILE_NAME
+    );
+    let mut feed = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+    );
+    feed.push_str(&format!(
+        "  <title>{title}</title>\n  <id>{id}</id>\n  <link rel=\"self\" href=\"{id}\"/>\n  \
+         <link href=\"{link}\"/>\n  <updated>{updated}</updated>\n  {author}\n",
+        title = utils::escape_html(title),
+        id = utils::escape_html(&feed_url),
+        link = utils::escape_html(sitemap::absolute_url(base_url, path)),
+        updated = timestamp(Some(updated)),
+        author = authors(&[author.to_owned()]),
+    ));
+    for entry in &entries {
+        let url = utils::escape_html(sitemap::absolute_url(base_url, &entry.path));
+        feed.push_str("  <entry>\n");
+        feed.push_str(&format!(
+            "    <title>{title}</title>\n    <id>{url}</id>\n    <link href=\"{url}\"/>\n    \
+             <updated>{updated}</updated>\n",
+            title = utils::escape_html(&entry.title),
+            url = url,
+            updated = timestamp(entry.date),
+        ));
+        if !entry.authors.is_empty() {
+            feed.push_str(&format!("    {}\n", authors(&entry.authors)));
+        }
+        if let Some(summary) = &entry.summary {
+            feed.push_str(&format!(
+                "    <summary>{}</summary>\n",
+                utils::escape_html(summary)
+            ));
+        }
+        feed.push_str("  </entry>\n");
+    }
+    feed.push_str("</feed>\n");
+
+    output.write(&Path::new(path).join(FEED_FILE_NAME), feed.as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::output::MemoryOutput;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(slug: &str, days: Option<u64>) -> FeedEntry {
+        FeedEntry {
+            title: format!("Release {}", slug),
+            path: format!("news/{}/", slug),
+            date: days.map(|days| UNIX_EPOCH + Duration::from_secs(days * 86400)),
+            authors: vec![String::from("Ann & Bob")],
+            summary: Some(String::from("Fixes & features")),
+        }
+    }
+
+    #[test]
+    fn write_feed_newest_first() {
+        let output = MemoryOutput::new();
+        let entries = vec![
+            entry("v1", Some(19000)),
+            entry("v0", None),
+            entry("v2", Some(19500)),
+        ];
+        let build = UNIX_EPOCH + Duration::from_secs(20000 * 86400);
+        write_feed(
+            &output,
+            "https://example.com/",
+            "news/",
+            "News",
+            "Docket",
+            build,
+            entries,
+        )
+        .unwrap();
+
+        let feed = String::from_utf8(output.get("news/feed.xml").unwrap()).unwrap();
+        assert!(feed.contains("<title>News</title>"));
+        assert!(feed.contains("<id>https://example.com/news/feed.xml</id>"));
+        assert!(feed.contains("<link href=\"https://example.com/news/\"/>"));
+        assert!(feed.contains("<updated>2023-05-23T00:00:00Z</updated>"));
+        assert!(feed.contains("<updated>2024-10-04T00:00:00Z</updated>"));
+        assert!(!feed.contains("1970"));
+        assert!(feed.contains("  <author><name>Docket</name></author>\n"));
+        assert!(feed.contains("    <author><name>Ann &amp; Bob</name></author>\n"));
+        assert!(feed.contains("<summary>Fixes &amp; features</summary>"));
+        let titles: Vec<_> = feed
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("<title>Release "))
+            .collect();
+        // The undated entry is the newest, from the time of the build.
+        assert_eq!(vec!["v0</title>", "v2</title>", "v1</title>"], titles);
+    }
+}
@@ -0,0 +1,154 @@
+//! Page Front Matter
+//!
+//! Pages can start with a block of TOML between two `+++` lines to give
+//! settings which can't be inferred from the page's markdown. The block is
+//! removed before the page is rendered.
+//!
+//! ```markdown
+//! +++
+//! aliases = ["old-name/"]
+//! +++
+//!
+//! # My Page
+//! ```
+
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use toml::value::{Datetime, Offset};
+
+use crate::utils;
+
+/// The line which opens and closes a front matter block
+const DELIMITER: &str = "+++";
+
+/// Page Front Matter
+///
+/// The settings given at the start of a page. Every setting is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct FrontMatter {
+    /// Paths, relative to the site root, which should redirect to this page
+    pub aliases: Vec<String>,
+    /// A short summary of the page, for search engines and link previews
+    pub description: Option<String>,
+    /// When the page was published, such as for a post in a news feed
+    pub date: Option<Datetime>,
+    /// On a bale's index page, publish a feed of the bale's pages
+    pub feed: bool,
+    /// The people who wrote the page, in place of its contributors from git
+    pub authors: Vec<String>,
+}
+
+impl FrontMatter {
+    /// Parse the TOML `text` of a front matter block
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Get the `date` as a `SystemTime`. Dates without a time are taken to be
+    /// at midnight UTC.
+    pub fn date_time(&self) -> Option<SystemTime> {
+        let datetime = self.date.as_ref()?;
+        let date = datetime.date?;
+        let mut seconds = datetime.time.map_or(0, |time| {
+            i64::from(time.hour) * 3600
+                + i64::from(time.minute) * 60
+                + i64::from(time.second.unwrap_or(0))
+        });
+        if let Some(Offset::Custom { minutes }) = datetime.offset {
+            seconds -= i64::from(minutes) * 60;
+        }
+        utils::time_from_civil(
+            i64::from(date.year),
+            u64::from(date.month),
+            u64::from(date.day),
+            seconds,
+        )
+    }
+}
+
+/// Split the Front Matter from a Page
+///
+/// Returns the text of the front matter block, if the page has one, the
+/// markdown following it, and the number of lines the block took up. A block
+/// which is never closed isn't treated as front matter.
+pub(crate) fn split(markdown: &str) -> (Option<&str>, &str, usize) {
+    let mut start = 0;
+    let mut offset = 0;
+    for (idx, line) in markdown.split_inclusive('\n').enumerate() {
+        let end = offset + line.len();
+        if line.trim_end() == DELIMITER {
+            if idx == 0 {
+                start = end;
+            } else {
+                return (Some(&markdown[start..offset]), &markdown[end..], idx + 1);
+            }
+        } else if idx == 0 {
+            break;
+        }
+        offset = end;
+    }
+    (None, markdown, 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_without_front_matter() {
+        assert_eq!((None, "# Title\n", 0), split("# Title\n"));
+        assert_eq!((None, "", 0), split(""));
+        assert_eq!((None, "+++\nno end\n", 0), split("+++\nno end\n"));
+        assert_eq!(
+            (None, "# Title\n+++\n+++\n", 0),
+            split("# Title\n+++\n+++\n")
+        );
+    }
+
+    #[test]
+    fn split_with_front_matter() {
+        let page = "+++\naliases = [\"old/\"]\n+++\n# Title\n";
+        assert_eq!(
+            (Some("aliases = [\"old/\"]\n"), "# Title\n", 3),
+            split(page)
+        );
+        assert_eq!((Some(""), "body", 2), split("+++\r\n+++\r\nbody"));
+    }
+
+    #[test]
+    fn parse_front_matter() {
+        let front = FrontMatter::parse("aliases = [\"old/\", \"/older\"]\n").unwrap();
+        assert_eq!(vec!["old/", "/older"], front.aliases);
+        assert_eq!(None, front.description);
+        assert!(FrontMatter::parse("").unwrap().aliases.is_empty());
+        let front = FrontMatter::parse("description = \"A page.\"\n").unwrap();
+        assert_eq!(Some("A page."), front.description.as_deref());
+        assert!(!front.feed);
+        assert!(FrontMatter::parse("feed = true").unwrap().feed);
+        let front = FrontMatter::parse("authors = [\"Ann\"]\n").unwrap();
+        assert_eq!(vec!["Ann"], front.authors);
+    }
+
+    #[test]
+    fn front_matter_dates() {
+        let date = |text: &str| {
+            FrontMatter::parse(text)
+                .unwrap()
+                .date_time()
+                .and_then(utils::format_timestamp)
+        };
+        assert_eq!(None, date(""));
+        assert_eq!(
+            Some(String::from("2024-03-01T00:00:00Z")),
+            date("date = 2024-03-01")
+        );
+        assert_eq!(
+            Some(String::from("2024-03-01T09:30:00Z")),
+            date("date = 2024-03-01T10:30:00+01:00")
+        );
+        assert_eq!(None, date("date = 10:30:00"));
+        assert!(FrontMatter::parse("aliases = 1").is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Git History
+//!
+//! File system modification times are reset by every fresh clone or CI
+//! checkout. This module reads the history of the documentation from the git
+//! repository that contains it instead. The `git` command line is run once for
+//! the whole source tree, and the results are indexed by path.
+//!
+//! Older versions of the documentation can also be read straight from the
+//! repository, without checking them out, so several releases can be built
+//! side by side.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::source::MemoryFs;
+
+/// The history of a single file
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FileHistory {
+    /// The time of the most recent commit to touch the file
+    pub modified: Option<SystemTime>,
+    /// The names of everyone who has committed to the file, most recent first
+    pub authors: Vec<String>,
+}
+
+/// Git History
+///
+/// The commit history of every file within a directory, indexed by the file's
+/// path relative to that directory.
+#[derive(Debug, Default)]
+pub(crate) struct GitHistory(HashMap<PathBuf, FileHistory>);
+
+impl GitHistory {
+    /// Load the History for a Directory
+    ///
+    /// Runs `git log` in the given directory, from `rev` if given or the
+    /// current `HEAD` otherwise. Fails if `git` isn't installed or the
+    /// directory isn't within a git repository.
+    pub fn load<P: AsRef<Path>>(dir: P, rev: Option<&str>) -> io::Result<Self> {
+        let output = Command::new("git")
+            .args(["-c", "core.quotepath=off", "log", "--format=%x00%ct%x09%aN"])
+            .args(["--name-only", "--relative"])
+            .args(rev)
+            .args(["--", "."])
+            .current_dir(dir)
+            .output()?;
+        check_status(&output)?;
+        Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parse the Output of `git log`
+    ///
+    /// Each commit starts with a NUL, followed by the commit time and author
+    /// name separated by a tab. The paths touched by the commit follow on
+    /// their own lines. Commits are listed most recent first.
+    fn parse(log: &str) -> Self {
+        let mut files: HashMap<PathBuf, FileHistory> = HashMap::new();
+        for commit in log.split('\0').skip(1) {
+            let mut lines = commit.lines();
+            let Some((time, author)) = lines.next().and_then(|l| l.split_once('\t')) else {
+                continue;
+            };
+            let time = time
+                .parse()
+                .ok()
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+            for path in lines.filter(|l| !l.is_empty()) {
+                let history = files.entry(PathBuf::from(path)).or_default();
+                if history.modified.is_none() {
+                    history.modified = time;
+                }
+                if !history.authors.iter().any(|a| a == author) {
+                    history.authors.push(author.to_owned());
+                }
+            }
+        }
+        GitHistory(files)
+    }
+
+    /// Get the history for the given `path`, if it has any
+    pub fn file(&self, path: &Path) -> Option<&FileHistory> {
+        self.0.get(path)
+    }
+}
+
+/// Read a Directory from a Git Revision
+///
+/// Reads every file within `dir` as it was at `rev`, such as a tag or branch,
+/// without checking it out. The files are keyed by their path joined onto
+/// `dir`, the same as they would be if read from the disk.
+pub(crate) fn read_tree<P: AsRef<Path>>(dir: P, rev: &str) -> io::Result<MemoryFs> {
+    let dir = dir.as_ref();
+    let listing = Command::new("git")
+        .args(["ls-tree", "-r", "-z", rev, "."])
+        .current_dir(dir)
+        .output()?;
+    check_status(&listing)?;
+    let blobs = parse_tree_listing(&listing.stdout);
+    if blobs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no documentation in {:?} at `{}`", dir, rev),
+        ));
+    }
+
+    // Read all the blobs with a single `cat-file`. The request is written on
+    // another thread so a full output pipe can't block it.
+    let mut child = Command::new("git")
+        .args(["cat-file", "--batch"])
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("cat-file stdin is piped");
+    let request: String = blobs.iter().map(|(id, _)| format!("{}\n", id)).collect();
+    let writer = thread::spawn(move || stdin.write_all(request.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer
+        .join()
+        .map_err(|_| io::Error::other("writing to git cat-file panicked"))??;
+    check_status(&output)?;
+
+    let contents = parse_batch(&output.stdout, blobs.len())?;
+    Ok(blobs
+        .into_iter()
+        .zip(contents)
+        .map(|((_, path), contents)| (dir.join(path), contents))
+        .collect())
+}
+
+/// Parse the Output of `git ls-tree -r -z`
+///
+/// Each entry is the object's mode, type, and id separated by spaces, then a
+/// tab and the path. Entries are separated by NULs. Returns the id and path of
+/// each blob, skipping other objects such as submodules.
+fn parse_tree_listing(listing: &[u8]) -> Vec<(String, PathBuf)> {
+    String::from_utf8_lossy(listing)
+        .split('\0')
+        .filter_map(|entry| {
+            let (info, path) = entry.split_once('\t')?;
+            let mut info = info.split(' ');
+            let (_mode, kind, id) = (info.next()?, info.next()?, info.next()?);
+            (kind == "blob").then(|| (id.to_owned(), PathBuf::from(path)))
+        })
+        .collect()
+}
+
+/// Parse the Output of `git cat-file --batch`
+///
+/// Each object is a header line of its id, type, and size, followed by its
+/// contents and a newline. Returns the contents of the first `count` objects.
+fn parse_batch(mut output: &[u8], count: usize) -> io::Result<Vec<Vec<u8>>> {
+    let malformed = || io::Error::new(io::ErrorKind::InvalidData, "malformed git cat-file output");
+    let mut objects = Vec::with_capacity(count);
+    for _ in 0..count {
+        let end = output
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(malformed)?;
+        let header = String::from_utf8_lossy(&output[..end]);
+        let size: usize = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(|| io::Error::other(format!("git cat-file: {}", header)))?;
+        let start = end + 1;
+        let contents = output.get(start..start + size).ok_or_else(malformed)?;
+        objects.push(contents.to_owned());
+        output = output.get(start + size + 1..).unwrap_or_default();
+    }
+    Ok(objects)
+}
+
+/// Turn a Failed `git` Command into an Error
+fn check_status(output: &std::process::Output) -> io::Result<()> {
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_empty_log() {
+        let history = GitHistory::parse("");
+        assert_eq!(None, history.file(Path::new("index.md")));
+    }
+
+    #[test]
+    fn parse_log_with_commits() {
+        let log = "\x00300\tAlex\n\nindex.md\nguide/intro.md\n\
+                   \x00200\tSam\n\nguide/intro.md\n\
+                   \x00100\tAlex\n\nguide/intro.md\nold.md\n";
+        let history = GitHistory::parse(log);
+
+        assert_eq!(
+            Some(&FileHistory {
+                modified: Some(UNIX_EPOCH + Duration::from_secs(300)),
+                authors: vec!["Alex".into()],
+            }),
+            history.file(Path::new("index.md"))
+        );
+        assert_eq!(
+            Some(&FileHistory {
+                modified: Some(UNIX_EPOCH + Duration::from_secs(300)),
+                authors: vec!["Alex".into(), "Sam".into()],
+            }),
+            history.file(&Path::new("guide").join("intro.md"))
+        );
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(100)),
+            history.file(Path::new("old.md")).and_then(|h| h.modified)
+        );
+        assert_eq!(None, history.file(Path::new("missing.md")));
+    }
+
+    #[test]
+    fn parse_tree_listing_blobs() {
+        let listing = b"100644 blob 1111\tindex.md\0\
+                        160000 commit 2222\tvendor/lib\0\
+                        100644 blob 3333\tguide/caf\xc3\xa9.md\0";
+        assert_eq!(
+            vec![
+                (String::from("1111"), PathBuf::from("index.md")),
+                (String::from("3333"), PathBuf::from("guide/café.md")),
+            ],
+            parse_tree_listing(listing)
+        );
+        assert!(parse_tree_listing(b"").is_empty());
+    }
+
+    #[test]
+    fn parse_batch_contents() {
+        let output = b"1111 blob 6\n# Home\n3333 blob 0\n\n";
+        assert_eq!(
+            vec![b"# Home".to_vec(), Vec::new()],
+            parse_batch(output, 2).unwrap()
+        );
+        let missing = b"4444 missing\n";
+        assert!(parse_batch(missing, 1).is_err());
+        assert!(parse_batch(b"1111 blob 60\n# Home\n", 1).is_err());
+    }
+}
@@ -7,21 +7,35 @@
 //! browser.
 //!
 //!  If the `syntect-hl` feature is enabled then both highlighters will be
-//!  available, and syntect perferred. HighlightJS is always avaiable.
+//!  available, and syntect perferred. HighlightJS is always avaiable, unless
+//!  the site is built offline. In that case code blocks are left plain.
 
 use std::io::Write;
 
 use log::debug;
 use pulldown_cmark::{CodeBlockKind, Event, Tag};
 
-use crate::asset::Asset;
+use crate::{asset::Asset, config::Config};
 
-pub(crate) trait Highlighter {
+/// Highlighter Trait
+///
+/// Highlighters turn the contents of code blocks into HTML. Code blocks are
+/// highlighted as each page is parsed, and the highlighter is asked for any
+/// assets and page headers the highlighted HTML needs.
+pub trait Highlighter {
     /// # Highlight a Code Block
     ///
     /// Returns a list of the events to emit to the TOC to represent the block.
     fn hl_codeblock<'a>(&self, name: Option<&str>, block: &str) -> Vec<Event<'_>>;
 
+    /// # Check Language Support
+    ///
+    /// Returns false if code blocks in the given language can't be
+    /// highlighted. Code blocks in unknown languages are reported as warnings.
+    fn supports(&self, _lang: &str) -> bool {
+        true
+    }
+
     /// # Get the Assets Required by this Highlighter
     ///
     /// Returns a list of assets to be written to the output root alongside
@@ -36,6 +50,7 @@ pub(crate) trait Highlighter {
 }
 
 pub use js_hl::HighlightJsHighlighter;
+pub use plain_hl::PlainHighlighter;
 #[cfg(feature = "syntect-hl")]
 pub use syntect_hl::SyntectHighlighter;
 
@@ -60,6 +75,10 @@ mod syntect_hl {
     /// The name of the generated CSS asset file.
     const HIGHLIGHT_CSS: &str = "highlight.css";
 
+    /// Languages used to mark a block as plain text. Syntect renders these
+    /// unhighlighted, so they aren't reported as unknown.
+    const PLAIN_LANGUAGES: &[&str] = &["nohighlight", "plain", "text"];
+
     fn class_style() -> ClassStyle {
         ClassStyle::SpacedPrefixed { prefix: "hl-" }
     }
@@ -130,6 +149,10 @@ mod syntect_hl {
             vec![Event::Html(html.into())]
         }
 
+        fn supports(&self, lang: &str) -> bool {
+            PLAIN_LANGUAGES.contains(&lang) || self.ss.find_syntax_by_token(lang).is_some()
+        }
+
         fn assets(&self) -> std::io::Result<Vec<Asset>> {
             let css = self.get_css()?.to_owned();
             Ok(vec![Asset::generated(HIGHLIGHT_CSS, css)])
@@ -179,6 +202,34 @@ mod js_hl {
     }
 }
 
+mod plain_hl {
+    use std::io::Write;
+
+    use pulldown_cmark::Event;
+
+    use super::{to_default_events, Asset, Highlighter};
+
+    /// Plain Highlighter
+    ///
+    /// Renders code blocks without any highlighting. Used when no highlighter
+    /// is available.
+    pub struct PlainHighlighter;
+
+    impl Highlighter for PlainHighlighter {
+        fn hl_codeblock(&self, name: Option<&str>, block: &str) -> Vec<Event<'_>> {
+            to_default_events(name, block)
+        }
+
+        fn assets(&self) -> std::io::Result<Vec<Asset>> {
+            Ok(vec![])
+        }
+
+        fn write_header(&self, _out: &mut dyn Write, _root: &str) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
 fn to_default_events<'a, 'b>(name: Option<&'a str>, block: &'a str) -> Vec<Event<'b>> {
     let kind = match name {
         Some(name) => CodeBlockKind::Fenced(name.to_owned().into()),
@@ -193,9 +244,11 @@ fn to_default_events<'a, 'b>(name: Option<&'a str>, block: &'a str) -> Vec<Event
 
 /// # Get the Active Highlighter
 ///
-/// Returns a reference to a shared highlighter.
-pub(crate) fn get_hilighter() -> &'static dyn Highlighter {
+/// Returns a reference to a shared highlighter. The Javascript highlighter is
+/// loaded from a CDN, so it isn't used if the `config` is offline.
+pub(crate) fn get_hilighter(config: &Config) -> &'static dyn Highlighter {
     static GLOBAL_JS_HL: HighlightJsHighlighter = HighlightJsHighlighter;
+    static GLOBAL_PLAIN_HL: PlainHighlighter = PlainHighlighter;
 
     #[cfg(feature = "syntect-hl")]
     if std::env::var("DOCKET_FORCE_JS_HL").is_err() {
@@ -206,6 +259,11 @@ pub(crate) fn get_hilighter() -> &'static dyn Highlighter {
         return &*GLOBAL_SYNTECT_HL;
     }
 
+    if config.offline {
+        debug!("Javascript highlighter not available offline.");
+        return &GLOBAL_PLAIN_HL;
+    }
+
     debug!("Using Javascript highlighter.");
     &GLOBAL_JS_HL
 }
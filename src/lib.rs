@@ -0,0 +1,60 @@
+//! Markdown to HTML Documentation Generator
+//!
+//! Docket renders a directory of markdown files into a static HTML site. The
+//! `docket` command line tool is a thin wrapper around this library, which can
+//! also be used to render documentation programmatically:
+//!
+//! ```no_run
+//! use docket::{Docket, RenderContext};
+//!
+//! # fn main() -> docket::Result<()> {
+//! let docs = Docket::open("docs/")?;
+//! docs.render_with(RenderContext::builder("build/").site_name("My Project"))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Pages are written out by a `Layout`, and code blocks highlighted by a
+//! `Highlighter`. Custom implementations of either can be given to the
+//! `RenderContextBuilder`. Documentation can be read from somewhere other than
+//! the local disk by opening it with a custom `SourceFs`, and the site written
+//! somewhere other than a directory with a custom `OutputSink`.
+
+#![deny(missing_docs)]
+
+mod asset;
+mod config;
+mod diagnostics;
+mod diagram;
+mod docket;
+mod doctree;
+mod error;
+mod feed;
+mod frontmatter;
+mod git;
+mod highlight;
+mod locale;
+mod output;
+mod redirect;
+mod render;
+mod search;
+mod sitemap;
+mod source;
+mod toc;
+mod translation;
+mod utils;
+
+pub use crate::asset::{Asset, GeneratedAsset, InternalAsset};
+pub use crate::diagnostics::{Diagnostic, Position, Severity};
+pub use crate::diagram::DiagramCache;
+pub use crate::docket::Docket;
+pub use crate::doctree::Page;
+pub use crate::error::{Error, Result};
+pub use crate::highlight::Highlighter;
+#[cfg(feature = "archive")]
+pub use crate::output::{ArchiveFormat, ArchiveOutput};
+pub use crate::output::{DirectoryOutput, MemoryOutput, OutputSink};
+pub use crate::render::{
+    CardSummary, Layout, PageKind, RenderContext, RenderContextBuilder, RenderState,
+};
+pub use crate::source::{DiskFs, MemoryFs, Metadata, SourceFs};
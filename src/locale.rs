@@ -0,0 +1,322 @@
+//! Interface Strings
+//!
+//! The text of the default layout itself, such as "On this Page" or "Last
+//! updated:", is shown in the language of the documentation. Docket has
+//! built-in strings for a few languages, and falls back to English for the
+//! rest. Any of the strings can be overridden, or another language added, in
+//! a `strings.toml` in the documentation root with a table for each language:
+//!
+//! ```toml
+//! [en]
+//! on-this-page = "Contents"
+//!
+//! [pt]
+//! on-this-page = "Nesta página"
+//! ```
+
+use std::time::SystemTime;
+
+use toml::{Table, Value};
+
+use crate::{
+    config,
+    diagnostics::Diagnostic,
+    error::{Error, Result},
+    source::Source,
+    utils,
+};
+
+/// The name of the file of interface strings in the documentation root
+pub(crate) const STRINGS_FILE_NAME: &str = "strings.toml";
+
+/// The built-in strings, in the same format as a `strings.toml`
+const BUILT_IN: &str = include_str!("../assets/strings.toml");
+
+/// The language every other falls back to. It has every string.
+const BASE_LANG: &str = "en";
+
+/// Interface Strings for Every Language
+///
+/// Holds the built-in strings, along with any given by the documentation.
+#[derive(Debug)]
+pub(crate) struct Locales {
+    /// The strings Docket ships with
+    built_in: Table,
+    /// The strings from the documentation's `strings.toml`
+    overrides: Table,
+}
+
+impl Locales {
+    /// Create Locales with Only the Built-in Strings
+    pub(crate) fn built_in() -> Self {
+        Locales {
+            built_in: parse(BUILT_IN).expect("built-in strings are valid"),
+            overrides: Table::new(),
+        }
+    }
+
+    /// Load the Locales for a Source
+    ///
+    /// Reads the configured `strings` file, or the `strings.toml` in the
+    /// documentation root if there is one. Strings which aren't known, or have
+    /// the wrong type, are reported as configuration errors.
+    pub fn load(source: &Source) -> Result<Self> {
+        let mut locales = Self::built_in();
+        let path = source.root().join(source.config().strings_file());
+        if !source.fs().is_file(&path) {
+            if let Some(strings) = &source.config().strings {
+                source.report(
+                    Diagnostic::warning(format!(
+                        "strings file `{}` does not exist",
+                        strings.display()
+                    ))
+                    .in_file(source.root().join(config::CONFIG_FILE_NAME)),
+                );
+            }
+            return Ok(locales);
+        }
+        let contents = source
+            .fs()
+            .read_to_string(&path)
+            .map_err(|e| Error::in_file(&path, e))?;
+        locales.overrides = parse(&contents)
+            .and_then(|overrides| locales.check(overrides))
+            .map_err(|e| Error::InvalidConfig(path, e))?;
+        Ok(locales)
+    }
+
+    /// Check that each of the `overrides` replaces a known string with a
+    /// value of the same type.
+    fn check(&self, overrides: Table) -> std::result::Result<Table, String> {
+        let base = self.built_in.get(BASE_LANG).and_then(Value::as_table);
+        for (lang, strings) in &overrides {
+            for (key, value) in strings.as_table().into_iter().flatten() {
+                let expected = base
+                    .and_then(|base| base.get(key))
+                    .ok_or_else(|| format!("`{}` isn't a known string, in `[{}]`", key, lang))?;
+                if !same_shape(value, expected) {
+                    return Err(format!(
+                        "`{}` should be a {}, in `[{}]`",
+                        key,
+                        describe(expected),
+                        lang
+                    ));
+                }
+            }
+        }
+        Ok(overrides)
+    }
+
+    /// Get the Strings for a Language
+    ///
+    /// Looks up the strings for the language tag `lang`, such as `fr-CA`.
+    /// Strings the language doesn't have are taken from its primary language,
+    /// `fr`, and then from English. Tags are matched ignoring case.
+    pub fn strings(&self, lang: &str) -> Strings {
+        let lang = lang.to_ascii_lowercase();
+        let primary = lang.split('-').next().unwrap_or_default();
+        let mut strings = Table::new();
+        for tag in [BASE_LANG, primary, &lang] {
+            for locale in [&self.built_in, &self.overrides] {
+                if let Some(Value::Table(table)) = locale.get(tag) {
+                    strings.extend(table.clone());
+                }
+            }
+        }
+        Strings(strings)
+    }
+}
+
+/// Parse a Strings File
+///
+/// Each top-level key must be a table of strings for a language. The language
+/// tags are lowercased so they can be matched ignoring case.
+fn parse(text: &str) -> std::result::Result<Table, String> {
+    let table: Table = toml::from_str(text).map_err(|e| e.message().to_owned())?;
+    table
+        .into_iter()
+        .map(|(lang, strings)| match strings {
+            Value::Table(_) => Ok((lang.to_ascii_lowercase(), strings)),
+            _ => Err(format!("`{}` should be a table of strings", lang)),
+        })
+        .collect()
+}
+
+/// Check if `value` has the same type as `expected`, including the length of
+/// lists.
+fn same_shape(value: &Value, expected: &Value) -> bool {
+    match (value, expected) {
+        (Value::Array(values), Value::Array(expected)) => {
+            values.len() == expected.len() && values.iter().all(Value::is_str)
+        }
+        _ => value.same_type(expected),
+    }
+}
+
+/// Describe the type of a string's value, for error messages
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Array(values) => format!("list of {} strings", values.len()),
+        _ => String::from("string"),
+    }
+}
+
+/// The Interface Strings for One Language
+#[derive(Debug)]
+pub(crate) struct Strings(Table);
+
+impl Strings {
+    /// Get the string with the given `key`, such as `on-this-page`
+    pub fn get(&self, key: &str) -> &str {
+        self.0.get(key).and_then(Value::as_str).unwrap_or_default()
+    }
+
+    /// Get the string with the given `key`, escaped for use in HTML text or
+    /// attributes.
+    pub fn html(&self, key: &str) -> String {
+        utils::escape_html(self.get(key))
+    }
+
+    /// Iterate over the keys of the strings, skipping any lists
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .iter()
+            .filter(|(_, value)| value.is_str())
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Format a Date
+    ///
+    /// Writes the day of `t` using the `date-format`, which can include the
+    /// `{year}`, `{month}`, `{month-name}`, and `{day}`, or the whole
+    /// `{iso-date}`. Returns `None` for times before the Unix epoch.
+    pub fn format_date(&self, t: SystemTime) -> Option<String> {
+        let (year, month, day) = utils::civil_date(t)?;
+        let month_name = self
+            .0
+            .get("months")
+            .and_then(Value::as_array)
+            .and_then(|months| months.get(month as usize - 1))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        Some(
+            self.get("date-format")
+                .replace("{iso-date}", &utils::format_date(t)?)
+                .replace("{year}", &year.to_string())
+                .replace("{month-name}", month_name)
+                .replace("{month}", &month.to_string())
+                .replace("{day}", &day.to_string()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::source::MemoryFs;
+    use std::{
+        path::Path,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    fn load(strings: &str) -> Result<Locales> {
+        let fs: MemoryFs = [("docs/strings.toml", strings)].into_iter().collect();
+        Locales::load(&Source::open("docs", Box::new(fs)).unwrap())
+    }
+
+    #[test]
+    fn built_in_languages_are_complete() {
+        let locales = Locales::built_in();
+        let base = locales.strings(BASE_LANG);
+        for (lang, strings) in &locales.built_in {
+            for key in strings.as_table().unwrap().keys() {
+                assert!(base.0.contains_key(key), "unknown `{}` in `{}`", key, lang);
+            }
+            if lang.contains('-') {
+                continue;
+            }
+            let locale = locales.strings(lang);
+            assert_eq!(base.0.len(), strings.as_table().unwrap().len(), "{}", lang);
+            assert!(locale.format_date(UNIX_EPOCH).is_some());
+            assert!(locale.get("rendered-by").contains("{docket}"));
+            assert!(locale.get("not-found-message").contains("({home})"));
+        }
+    }
+
+    #[test]
+    fn strings_fall_back_to_primary_language() {
+        let locales = Locales::built_in();
+        assert_eq!("On this Page", locales.strings("en").get("on-this-page"));
+        assert_eq!(
+            "Sur cette page",
+            locales.strings("fr-CA").get("on-this-page")
+        );
+        assert_eq!("Suche", locales.strings("DE").get("search"));
+        assert_eq!("Search", locales.strings("pt-BR").get("search"));
+        assert_eq!(
+            "Permalink to this heading",
+            locales.strings("").get("permalink")
+        );
+    }
+
+    #[test]
+    fn format_localised_dates() {
+        let locales = Locales::built_in();
+        let date = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let format = |lang: &str| locales.strings(lang).format_date(date).unwrap();
+        assert_eq!("November 14, 2023", format("en"));
+        assert_eq!("14 November 2023", format("en-GB"));
+        assert_eq!("14. November 2023", format("de"));
+        assert_eq!("14 novembre 2023", format("fr"));
+        assert_eq!("2023年11月14日", format("ja"));
+        assert_eq!(
+            None,
+            locales
+                .strings("en")
+                .format_date(UNIX_EPOCH - Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn override_strings() {
+        let locales = load(
+            "[EN]\non-this-page = \"Contents\"\ndate-format = \"{iso-date}\"\n\n\
+             [pt]\non-this-page = \"Nesta página\"\n",
+        )
+        .unwrap();
+        let en = locales.strings("en-US");
+        assert_eq!("Contents", en.get("on-this-page"));
+        assert_eq!("Next", en.get("next"));
+        assert_eq!(Some(String::from("1970-01-01")), en.format_date(UNIX_EPOCH));
+        assert_eq!("Nesta página", locales.strings("pt").get("on-this-page"));
+        assert_eq!("Suivant", locales.strings("fr").get("next"));
+        assert!(locales.strings("pt").keys().any(|key| key == "search"));
+        assert!(!locales.strings("pt").keys().any(|key| key == "months"));
+    }
+
+    #[test]
+    fn reject_invalid_strings() {
+        for strings in [
+            "on-this-page = \"Contents\"",
+            "[en]\non-this-pgae = \"Contents\"",
+            "[en]\nnext = 1",
+            "[en]\nmonths = [\"Jan\"]",
+        ] {
+            match load(strings) {
+                Err(Error::InvalidConfig(path, _)) => {
+                    assert_eq!(Path::new("docs/strings.toml"), path)
+                }
+                other => panic!("Expected {:?} to be invalid, got {:?}", strings, other),
+            }
+        }
+    }
+
+    #[test]
+    fn escape_strings_for_html() {
+        let locales = load("[en]\nsearch = \"Find <it> 'now'\"").unwrap();
+        assert_eq!(
+            "Find &lt;it&gt; &#39;now&#39;",
+            locales.strings("en").html("search")
+        );
+    }
+}
@@ -1,25 +1,18 @@
 //! Markdown to HTML Documentation Generator
+//!
+//! The `docket` command line tool. Parses the command line and builds the
+//! documentation with the `docket` library, optionally watching for changes.
 
 #![deny(missing_docs)]
 
 mod args;
-mod asset;
-mod docket;
-mod doctree;
-mod error;
-mod highlight;
-mod render;
-mod search;
-mod toc;
-mod utils;
 
 use std::{
     error::Error,
     path::{Path, PathBuf},
 };
 
-use crate::docket::Docket;
-use error::Result;
+use docket::{Diagnostic, DiagramCache, DirectoryOutput, Docket, RenderContext, Result};
 use log::{info, warn};
 
 /// On Error Behaviour
@@ -36,33 +29,59 @@ fn main() {
     init_logging();
 
     let args = args::from_command_line();
-    let source = utils::path_or_default(args.flag_source, ".");
-    let target = utils::path_or_default(args.flag_target, "build/");
+    let build = Build {
+        source: args::path_or_default(args.flag_source, "."),
+        target: args::path_or_default(args.flag_target, "build/"),
+        archive: args.flag_archive.map(PathBuf::from),
+        clean: args.flag_clean,
+        strict: args.flag_strict,
+        diagrams: DiagramCache::new(),
+    };
 
     handle_err(
         if args.flag_watch {
-            watch_and_build(&target, &source)
+            watch_and_build(&build)
         } else {
-            build(&source, &target)
+            build.run()
         },
         OnError::Exit,
     )
 }
 
+/// Build Settings
+///
+/// The options from the command line which control each build.
+struct Build {
+    /// The documentation directory
+    source: PathBuf,
+    /// The directory to write the site to
+    target: PathBuf,
+    /// The archive to write the site to, in place of the target
+    archive: Option<PathBuf>,
+    /// Remove files from the target which the build didn't write
+    clean: bool,
+    /// Treat warnings as errors
+    strict: bool,
+    /// Diagrams rendered by earlier builds
+    diagrams: DiagramCache,
+}
+
 /// Watch and Rebuild
 ///
 /// This opens a file watcher listening for changes in the source directory.
 /// When a file is changed we re-build the documentaiton tree.
-fn watch_and_build(target: &PathBuf, source: &PathBuf) -> Result<()> {
+fn watch_and_build(build: &Build) -> Result<()> {
     #[cfg(feature = "watch")]
     {
-        use crate::error::ResultExt;
+        let (source, target) = (&build.source, &build.target);
         use notify::{watcher, RecursiveMode, Watcher};
         use std::{fs, sync::mpsc::channel, time::Duration};
 
         // Create the target directory first, to ensure we can unwatch it if
-        // needed.
-        fs::create_dir_all(target).annotate_err("Error creating target directory")?;
+        // needed. Archives are written in place of the target.
+        if build.archive.is_none() {
+            fs::create_dir_all(target).annotate_err("Error creating target directory")?;
+        }
 
         let (tx, rx) = channel();
 
@@ -74,9 +93,20 @@ fn watch_and_build(target: &PathBuf, source: &PathBuf) -> Result<()> {
             .annotate_err("Error watching source directory")?;
         // Ignore the erorr here. It most likely means the source and target dir
         // don't overlap.
-        let _ = watcher.unwatch(target);
+        if build.archive.is_none() {
+            let _ = watcher.unwatch(target);
+        }
+
+        // Changes to the output, or the directories used to stage it, don't
+        // need a rebuild. Nor does writing the archive.
+        let output = DirectoryOutput::new(target);
+        let archive = build.archive.as_deref().map(archive_output).transpose()?;
+        let is_output = |path: &PathBuf| match &archive {
+            Some(archive) => archive.owns(path),
+            None => output.owns(path),
+        };
 
-        handle_err(build(source, target), OnError::Ignore);
+        handle_err(build.run(), OnError::Ignore);
         println!("Build complete. Watching for changes.");
         loop {
             match rx.recv() {
@@ -88,10 +118,17 @@ fn watch_and_build(target: &PathBuf, source: &PathBuf) -> Result<()> {
                     notify::DebouncedEvent::Error(e, path) => {
                         warn!("Watcher error at path {path:?}: {e}")
                     }
+                    notify::DebouncedEvent::Create(path)
+                    | notify::DebouncedEvent::Write(path)
+                    | notify::DebouncedEvent::Chmod(path)
+                    | notify::DebouncedEvent::Remove(path)
+                        if is_output(&path) => {}
+                    notify::DebouncedEvent::Rename(from, to)
+                        if is_output(&from) && is_output(&to) => {}
                     // Anything else means a rebuild
                     _ => {
                         println!("Rebuilding...");
-                        handle_err(build(source, target), OnError::Ignore);
+                        handle_err(build.run(), OnError::Ignore);
                         println!("Rebuild complete. Watching for changes.");
                     }
                 },
@@ -103,7 +140,26 @@ fn watch_and_build(target: &PathBuf, source: &PathBuf) -> Result<()> {
     #[cfg(not(feature = "watch"))]
     {
         eprintln!("Watch not supported. Performing on off build.");
-        build(source, target)
+        build.run()
+    }
+}
+
+/// Error Annotation Extension
+///
+/// Adds context to errors from the file watcher.
+#[cfg(feature = "watch")]
+trait ResultExt<T, A: Into<String>> {
+    fn annotate_err(self, anno: A) -> Result<T>;
+}
+
+#[cfg(feature = "watch")]
+impl<T, E, A> ResultExt<T, A> for std::result::Result<T, E>
+where
+    E: std::error::Error + 'static,
+    A: Into<String>,
+{
+    fn annotate_err(self, anno: A) -> Result<T> {
+        self.map_err(|err| docket::Error::annotated(anno, err))
     }
 }
 
@@ -120,14 +176,79 @@ fn handle_err(err: Result<()>, on_err: OnError) {
     }
 }
 
-/// Run a single pass of documentation generation
+impl Build {
+    /// Run a single pass of documentation generation
+    ///
+    /// This does the main job of rendering the documentaiton. Seprated into a
+    /// different function so we can use the `?` operator, and repeatedly call
+    /// if we're watching files and re-rendering on change.
+    fn run(&self) -> Result<()> {
+        let docket = Docket::open(&self.source)?;
+        let builder = RenderContext::builder(&self.target)
+            .strict(self.strict)
+            .diagram_cache(self.diagrams.clone());
+        // The output may be within the source, but isn't part of the site.
+        let diagnostics = match &self.archive {
+            Some(archive) => {
+                info!(
+                    "Rendering documenation from {:?} => {:?}",
+                    &self.source, &archive
+                );
+                let builder = builder.exclude(archive);
+                docket.render_with(builder.output(archive_output(archive)?))
+            }
+            None => {
+                info!(
+                    "Rendering documenation from {:?} => {:?}",
+                    &self.source, &self.target
+                );
+                let output = DirectoryOutput::new(&self.target).clean(self.clean);
+                docket.render_with(builder.exclude(&self.target).output(output))
+            }
+        }?;
+        report_diagnostics(&diagnostics);
+        Ok(())
+    }
+}
+
+/// Print the Warnings from a Build
+///
+/// Writes out each diagnostic, followed by a summary of how many there were.
+fn report_diagnostics(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        eprintln!("docket: {}", diagnostic);
+    }
+    match diagnostics.len() {
+        0 => (),
+        1 => eprintln!("docket: build finished with 1 warning"),
+        n => eprintln!("docket: build finished with {} warnings", n),
+    }
+}
+
+/// Create the Output for an Archive
+///
+/// Chooses the archive format based on the extension of the `archive` path.
+#[cfg(feature = "archive")]
+fn archive_output(archive: &Path) -> Result<docket::ArchiveOutput> {
+    use docket::{ArchiveFormat, ArchiveOutput, Error};
+
+    let format = ArchiveFormat::from_path(archive)
+        .ok_or_else(|| Error::UnknownArchiveFormat(archive.to_owned()))?;
+    Ok(ArchiveOutput::new(archive, format))
+}
+
+/// Create the Output for an Archive
 ///
-/// This does the main job of rendering the documentaiton. Seprated into a
-/// different function so we can use the `?` operator, and repeatedly call if
-/// we're watching files and re-rendering on change.
-fn build(source: &Path, target: &Path) -> Result<()> {
-    info!("Rendering documenation from {:?} => {:?}", &source, &target);
-    Docket::open(source)?.render(target)
+/// Archives aren't supported in this build, so this always fails.
+#[cfg(not(feature = "archive"))]
+fn archive_output(archive: &Path) -> Result<DirectoryOutput> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "can't write {:?}, archive output needs docket to be built with the `archive` feature",
+            archive
+        ),
+    ))?
 }
 
 /// Initialise logging
@@ -0,0 +1,463 @@
+//! Output Sinks
+//!
+//! Rendered pages and assets are written through the `OutputSink` trait rather
+//! than directly to the file system. This allows a site to be written to a
+//! directory, collected in memory, or packed into an archive. Paths given to a
+//! sink are always relative to the root of the site.
+
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "archive")]
+pub use archive::{ArchiveFormat, ArchiveOutput};
+
+/// Output Sink Trait
+///
+/// Receives each file of the rendered site.
+pub trait OutputSink {
+    /// Write a File
+    ///
+    /// Creates or replaces the file at `path`, relative to the site root, with
+    /// the given `contents`. Parent directories are created as needed.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Finish the Output
+    ///
+    /// Called once every file has been written.
+    fn finish(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Directory Output
+///
+/// Writes the site to a directory on the local disk. Files are first written
+/// to a staging directory alongside the target, which is swapped into place
+/// once the build finishes. A failed build leaves the existing site untouched.
+#[derive(Debug)]
+pub struct DirectoryOutput {
+    path: PathBuf,
+    clean: bool,
+    staged: Cell<bool>,
+}
+
+impl DirectoryOutput {
+    /// Create an Output Writing to the Directory at `path`
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        DirectoryOutput {
+            path: path.into(),
+            clean: false,
+            staged: Cell::new(false),
+        }
+    }
+
+    /// Set if files in the target not produced by the build should be removed
+    ///
+    /// By default any existing files the build doesn't replace are kept.
+    pub fn clean(mut self, clean: bool) -> Self {
+        self.clean = clean;
+        self
+    }
+
+    /// Check if `path` is within the output
+    ///
+    /// This includes the directories used to stage the build, as well as the
+    /// target directory itself.
+    pub fn owns(&self, path: &Path) -> bool {
+        [self.path.clone(), self.sibling("tmp"), self.sibling("old")]
+            .iter()
+            .filter_map(|dir| absolute(dir))
+            .any(|dir| path.starts_with(dir))
+    }
+
+    /// Get the path of the staging directory, creating it if needed
+    fn staging(&self) -> io::Result<PathBuf> {
+        let staging = self.sibling("tmp");
+        if !self.staged.get() {
+            // Any staging directory left here is from an earlier build which
+            // didn't finish.
+            if staging.exists() {
+                fs::remove_dir_all(&staging)?;
+            }
+            fs::create_dir_all(&staging)?;
+            self.staged.set(true);
+        }
+        Ok(staging)
+    }
+
+    /// Get the path of a hidden directory alongside the target
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let path = match self.path.file_name() {
+            Some(_) => self.path.clone(),
+            None => fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone()),
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!(".{}.docket-{}", name, suffix))
+    }
+}
+
+impl OutputSink for DirectoryOutput {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let path = self.staging()?.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        let staging = self.staging()?;
+        if self.path.exists() {
+            if !self.clean {
+                carry_over(&self.path, &staging)?;
+            }
+            let old = self.sibling("old");
+            if old.exists() {
+                fs::remove_dir_all(&old)?;
+            }
+            fs::rename(&self.path, &old)?;
+            fs::rename(&staging, &self.path)?;
+            fs::remove_dir_all(&old)?;
+        } else {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&staging, &self.path)?;
+        }
+        self.staged.set(false);
+        Ok(())
+    }
+}
+
+impl Drop for DirectoryOutput {
+    fn drop(&mut self) {
+        // The build failed part way through. Clear up the staged files.
+        if self.staged.get() {
+            let _ = fs::remove_dir_all(self.sibling("tmp"));
+        }
+    }
+}
+
+/// Get the absolute path of `dir`, which may not exist yet
+fn absolute(dir: &Path) -> Option<PathBuf> {
+    let parent = match dir.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(fs::canonicalize(parent).ok()?.join(dir.file_name()?))
+}
+
+/// Move the files in `from` which are missing in `to` across
+fn carry_over(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if !target.exists() {
+            fs::rename(entry.path(), target)?;
+        } else if target.is_dir() && entry.file_type()?.is_dir() {
+            carry_over(&entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// In-Memory Output
+///
+/// Collects the site in a map of path to contents. Clones share the same map,
+/// so a clone can be kept to inspect the files once rendering is complete.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryOutput(Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>);
+
+impl MemoryOutput {
+    /// Create an Empty Output
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get the contents of the file at `path`, if it has been written
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<Vec<u8>> {
+        self.0.lock().unwrap().get(path.as_ref()).cloned()
+    }
+
+    /// Get a copy of every file written so far
+    pub fn files(&self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl OutputSink for MemoryOutput {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(path.to_owned(), contents.to_owned());
+        Ok(())
+    }
+}
+
+/// Subdirectory Output
+///
+/// Writes into a subdirectory of a shared output, such as for one version of a
+/// site which is built alongside others. Finishing a subdirectory does
+/// nothing; the shared output is finished once every part has been written.
+pub(crate) struct SubdirectoryOutput {
+    inner: Rc<dyn OutputSink>,
+    prefix: PathBuf,
+}
+
+impl SubdirectoryOutput {
+    /// Create an Output Writing to `prefix` within `inner`
+    pub fn new<P: Into<PathBuf>>(inner: Rc<dyn OutputSink>, prefix: P) -> Self {
+        SubdirectoryOutput {
+            inner,
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl OutputSink for SubdirectoryOutput {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.inner.write(&self.prefix.join(path), contents)
+    }
+}
+
+#[cfg(feature = "archive")]
+mod archive {
+    use std::{
+        fs::File,
+        io::{self, Write},
+        path::{Path, PathBuf},
+    };
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::{MemoryOutput, OutputSink};
+
+    /// The kinds of archive the site can be written to
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ArchiveFormat {
+        /// A compressed `.zip` archive
+        Zip,
+        /// An uncompressed `.tar` archive
+        Tar,
+    }
+
+    impl ArchiveFormat {
+        /// Choose the archive format from the extension of `path`
+        pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+            match crate::utils::normalised_path_ext(path).as_deref() {
+                Some("zip") => Some(ArchiveFormat::Zip),
+                Some("tar") => Some(ArchiveFormat::Tar),
+                _ => None,
+            }
+        }
+    }
+
+    /// Archive Output
+    ///
+    /// Writes the site to a single archive file. Files are collected in memory
+    /// as they are rendered, and the archive written when the output is
+    /// finished. This keeps the archive's entries unique and in a stable
+    /// order.
+    #[derive(Debug)]
+    pub struct ArchiveOutput {
+        path: PathBuf,
+        format: ArchiveFormat,
+        files: MemoryOutput,
+    }
+
+    impl ArchiveOutput {
+        /// Create an Output Writing an Archive of `format` to `path`
+        pub fn new<P: Into<PathBuf>>(path: P, format: ArchiveFormat) -> Self {
+            ArchiveOutput {
+                path: path.into(),
+                format,
+                files: MemoryOutput::new(),
+            }
+        }
+
+        /// Check if `path` is the archive being written
+        pub fn owns(&self, path: &Path) -> bool {
+            super::absolute(&self.path).is_some_and(|archive| path == archive)
+        }
+
+        fn write_zip(&self, file: File) -> io::Result<()> {
+            let mut zip = ZipWriter::new(file);
+            for (path, contents) in self.files.files() {
+                zip.start_file(entry_name(&path), SimpleFileOptions::default())
+                    .map_err(io::Error::other)?;
+                zip.write_all(&contents)?;
+            }
+            zip.finish().map_err(io::Error::other)?;
+            Ok(())
+        }
+
+        fn write_tar(&self, file: File) -> io::Result<()> {
+            let mut tar = tar::Builder::new(file);
+            for (path, contents) in self.files.files() {
+                let mut header = tar::Header::new_ustar();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                tar.append_data(&mut header, entry_name(&path), &contents[..])?;
+            }
+            tar.finish()
+        }
+    }
+
+    impl OutputSink for ArchiveOutput {
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.files.write(path, contents)
+        }
+
+        fn finish(&self) -> io::Result<()> {
+            let file = File::create(&self.path)?;
+            match self.format {
+                ArchiveFormat::Zip => self.write_zip(file),
+                ArchiveFormat::Tar => self.write_tar(file),
+            }
+        }
+    }
+
+    /// Get the name of the archive entry for a site path. Archives always use
+    /// `/` to separate directories.
+    fn entry_name(path: &Path) -> String {
+        path.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn memory_output_collects_files() {
+        let output = MemoryOutput::new();
+        let shared = output.clone();
+        output
+            .write(Path::new("index.html"), b"<p>one</p>")
+            .unwrap();
+        output.write(Path::new("guide/index.html"), b"two").unwrap();
+        output
+            .write(Path::new("index.html"), b"<p>three</p>")
+            .unwrap();
+
+        assert_eq!(Some(b"<p>three</p>".to_vec()), shared.get("index.html"));
+        assert_eq!(2, shared.files().len());
+        assert_eq!(None, shared.get("missing.html"));
+    }
+
+    #[test]
+    fn subdirectory_output_prefixes_paths() {
+        let output = MemoryOutput::new();
+        let shared: Rc<dyn OutputSink> = Rc::new(output.clone());
+        let v1 = SubdirectoryOutput::new(shared.clone(), "v1");
+        v1.write(Path::new("guide/index.html"), b"guide").unwrap();
+        v1.finish().unwrap();
+        shared.write(Path::new("index.html"), b"root").unwrap();
+
+        assert_eq!(Some(b"guide".to_vec()), output.get("v1/guide/index.html"));
+        assert_eq!(2, output.files().len());
+    }
+
+    /// Create an empty directory to hold the output for a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("docket-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn directory_output_swaps_on_finish() {
+        let dir = test_dir("swap");
+        let target = dir.join("build");
+        fs::create_dir_all(target.join("old")).unwrap();
+        fs::write(target.join("index.html"), "old").unwrap();
+        fs::write(target.join("old/index.html"), "stale").unwrap();
+
+        let output = DirectoryOutput::new(&target);
+        output.write(Path::new("index.html"), b"new").unwrap();
+        output
+            .write(Path::new("guide/index.html"), b"guide")
+            .unwrap();
+        assert_eq!(
+            "old",
+            fs::read_to_string(target.join("index.html")).unwrap()
+        );
+        output.finish().unwrap();
+        drop(output);
+
+        assert_eq!(
+            "new",
+            fs::read_to_string(target.join("index.html")).unwrap()
+        );
+        assert!(target.join("guide/index.html").is_file());
+        assert!(target.join("old/index.html").is_file());
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn directory_output_clean_removes_stale_files() {
+        let dir = test_dir("clean");
+        let target = dir.join("build");
+        fs::create_dir_all(target.join("old")).unwrap();
+        fs::write(target.join("old/index.html"), "stale").unwrap();
+
+        let output = DirectoryOutput::new(&target).clean(true);
+        output.write(Path::new("index.html"), b"new").unwrap();
+        output.finish().unwrap();
+
+        assert!(target.join("index.html").is_file());
+        assert!(!target.join("old").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn directory_output_unfinished_leaves_target() {
+        let dir = test_dir("unfinished");
+        let target = dir.join("build");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("index.html"), "old").unwrap();
+
+        let output = DirectoryOutput::new(&target);
+        output.write(Path::new("index.html"), b"new").unwrap();
+        assert!(output.owns(
+            &dir.canonicalize()
+                .unwrap()
+                .join(".build.docket-tmp/index.html")
+        ));
+        drop(output);
+
+        assert_eq!(
+            "old",
+            fs::read_to_string(target.join("index.html")).unwrap()
+        );
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn archive_format_from_extension() {
+        assert_eq!(
+            Some(ArchiveFormat::Zip),
+            ArchiveFormat::from_path("site.zip")
+        );
+        assert_eq!(
+            Some(ArchiveFormat::Tar),
+            ArchiveFormat::from_path("out/SITE.TAR")
+        );
+        assert_eq!(None, ArchiveFormat::from_path("site.tar.gz"));
+        assert_eq!(None, ArchiveFormat::from_path("site"));
+    }
+}
@@ -0,0 +1,259 @@
+//! Redirects
+//!
+//! Page URLs come from file names, so renaming or moving a page changes its
+//! URL. Redirects keep old links working. They are declared as `aliases` in a
+//! page's front matter, or site-wide in the `[redirects]` table of
+//! `docket.toml`. Each redirect is written as a small HTML stub at the old
+//! path, and all of them are listed in a `_redirects` file at the root of the
+//! site for hosts which can perform the redirects themselves. When several
+//! versions or languages are built the one file lists the redirects of them
+//! all.
+
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{output::OutputSink, utils};
+
+/// The name of the file listing the redirects, in the root of the site
+const REDIRECTS_FILE_NAME: &str = "_redirects";
+
+/// A Redirect from an Old Path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Redirect {
+    /// The old path, relative to the site root, e.g. `guide/old-name/`
+    from: String,
+    /// The new location. Either a path relative to the site root or a URL
+    to: String,
+    /// The file the redirect was declared in
+    declared_in: PathBuf,
+}
+
+impl Redirect {
+    /// Create a Redirect
+    ///
+    /// Both `from` and `to` are normalised to directory paths relative to the
+    /// site root, the same as the paths pages are rendered to. Any fragment on
+    /// the target is kept. Targets which are full URLs are kept as they are.
+    pub fn new<P: Into<PathBuf>>(from: &str, to: &str, declared_in: P) -> Self {
+        let to = if to.contains("://") {
+            to.to_owned()
+        } else {
+            match to.split_once('#') {
+                Some((path, fragment)) => format!("{}#{}", normalise(path), fragment),
+                None => normalise(to),
+            }
+        };
+        Redirect {
+            from: normalise(from),
+            to,
+            declared_in: declared_in.into(),
+        }
+    }
+
+    /// Get the old path being redirected
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    /// Find a Path Outside the Site
+    ///
+    /// Returns the `from` or `to` path if it could lead outside the site root,
+    /// such as a path containing `..`. Redirects like this can't be written.
+    pub fn outside_site(&self) -> Option<&str> {
+        let within_site = |path: &str| {
+            Path::new(path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        };
+        if !within_site(&self.from) {
+            return Some(&self.from);
+        }
+        let to = self.to.split('#').next().unwrap_or_default();
+        if !self.to.contains("://") && !within_site(to) {
+            return Some(&self.to);
+        }
+        None
+    }
+
+    /// Get the file the redirect was declared in
+    pub fn declared_in(&self) -> &Path {
+        &self.declared_in
+    }
+
+    /// Move the Redirect Into a Subdirectory
+    ///
+    /// Returns a copy of the redirect with `prefix`, e.g. `v1/`, added to the
+    /// start of both paths. Targets which are full URLs are kept as they are.
+    pub fn with_prefix(&self, prefix: &str) -> Self {
+        let to = if self.to.contains("://") {
+            self.to.clone()
+        } else {
+            format!("{}{}", prefix, self.to)
+        };
+        Redirect {
+            from: format!("{}{}", prefix, self.from),
+            to,
+            declared_in: self.declared_in.clone(),
+        }
+    }
+
+    /// Get the link to the target, relative to the redirect stub
+    fn relative_target(&self) -> String {
+        if self.to.contains("://") {
+            return self.to.clone();
+        }
+        let mut link = "../".repeat(self.from.matches('/').count());
+        link.push_str(&self.to);
+        if link.is_empty() {
+            link.push_str("./");
+        }
+        link
+    }
+
+    /// Render the HTML stub written at the old path
+    fn stub(&self) -> String {
+        let target = utils::escape_html(self.relative_target());
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Redirecting&hellip;</title>
+<link rel="canonical" href="{target}">
+<meta http-equiv="refresh" content="0; url={target}">
+</head>
+<body>
+<p>This page has moved to <a href="{target}">{target}</a>.</p>
+</body>
+</html>
+"#
+        )
+    }
+}
+
+/// Normalise a Site Path
+///
+/// Strips any leading `/`, and makes sure a non-empty path ends with one.
+fn normalise(path: &str) -> String {
+    let mut path = path.trim().trim_start_matches('/').to_owned();
+    if !path.is_empty() && !path.ends_with('/') {
+        path.push('/');
+    }
+    path
+}
+
+/// Write out the Redirect Stubs
+///
+/// Writes a stub page at the old path of each redirect.
+pub(crate) fn write_stubs(output: &dyn OutputSink, redirects: &[Redirect]) -> io::Result<()> {
+    for redirect in redirects {
+        let path = PathBuf::from(&redirect.from).join("index.html");
+        output.write(&path, redirect.stub().as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Write out the Redirects Listing
+///
+/// Writes a `_redirects` file listing every redirect. Paths are taken from
+/// the root of the `output`. Nothing is written if there are no redirects.
+pub(crate) fn write_listing(output: &dyn OutputSink, redirects: &[Redirect]) -> io::Result<()> {
+    if redirects.is_empty() {
+        return Ok(());
+    }
+
+    let mut listing = String::new();
+    for redirect in redirects {
+        let to = if redirect.to.contains("://") {
+            redirect.to.clone()
+        } else {
+            format!("/{}", redirect.to)
+        };
+        listing.push_str(&format!("/{} {} 301\n", redirect.from, to));
+    }
+    output.write(REDIRECTS_FILE_NAME.as_ref(), listing.as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::output::MemoryOutput;
+
+    #[test]
+    fn normalise_redirect_paths() {
+        let redirect = Redirect::new("/old-name", "guide/new-name", "docket.toml");
+        assert_eq!("old-name/", redirect.from());
+        assert_eq!("../guide/new-name/", redirect.relative_target());
+
+        let redirect = Redirect::new("a/b/", "/", "docket.toml");
+        assert_eq!("../../", redirect.relative_target());
+
+        let redirect = Redirect::new("a", "b/#usage", "docket.toml");
+        assert_eq!("../b/#usage", redirect.relative_target());
+
+        let redirect = Redirect::new("a", "/guide#usage", "docket.toml");
+        assert_eq!("../guide/#usage", redirect.relative_target());
+
+        let redirect = Redirect::new("a", "https://example.com/", "docket.toml");
+        assert_eq!("https://example.com/", redirect.relative_target());
+    }
+
+    #[test]
+    fn write_redirect_stubs() {
+        let output = MemoryOutput::new();
+        let redirects = vec![
+            Redirect::new("old-name", "guide/new-name/", "docs/guide/new-name.md"),
+            Redirect::new("elsewhere", "https://example.com/", "docs/docket.toml"),
+            Redirect::new("usage", "/guide/#usage", "docs/docket.toml"),
+        ];
+        write_stubs(&output, &redirects).unwrap();
+        write_listing(&output, &redirects).unwrap();
+
+        let stub = String::from_utf8(output.get("old-name/index.html").unwrap()).unwrap();
+        assert!(stub.contains(r#"<meta http-equiv="refresh" content="0; url=../guide/new-name/">"#));
+        assert!(stub.contains(r#"<link rel="canonical" href="../guide/new-name/">"#));
+        assert_eq!(
+            "/old-name/ /guide/new-name/ 301\n/elsewhere/ https://example.com/ 301\n\
+             /usage/ /guide/#usage 301\n",
+            String::from_utf8(output.get("_redirects").unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn find_paths_outside_site() {
+        let outside = |from, to| {
+            Redirect::new(from, to, "docket.toml")
+                .outside_site()
+                .map(str::to_owned)
+        };
+        assert_eq!(None, outside("/old/", "/guide/#usage"));
+        assert_eq!(None, outside("", "https://example.com/../"));
+        assert_eq!(Some("../../etc/x/".into()), outside("../../etc/x", "/"));
+        assert_eq!(Some("a/../../x/".into()), outside("a/../../x", "/"));
+        assert_eq!(Some("../up/#top".into()), outside("old", "../up#top"));
+    }
+
+    #[test]
+    fn prefix_redirect_paths() {
+        let redirect = Redirect::new("old", "guide/#usage", "docket.toml").with_prefix("v1/en/");
+        assert_eq!("v1/en/old/", redirect.from());
+        assert_eq!("../../../v1/en/guide/#usage", redirect.relative_target());
+
+        let redirect = Redirect::new("", "en", "docket.toml").with_prefix("v1/");
+        assert_eq!("v1/", redirect.from());
+        assert_eq!("../v1/en/", redirect.relative_target());
+
+        let redirect = Redirect::new("a", "https://example.com/", "docket.toml").with_prefix("v1/");
+        assert_eq!("https://example.com/", redirect.relative_target());
+    }
+
+    #[test]
+    fn no_redirects_writes_nothing() {
+        let output = MemoryOutput::new();
+        write_stubs(&output, &[]).unwrap();
+        write_listing(&output, &[]).unwrap();
+        assert!(output.files().is_empty());
+    }
+}
@@ -4,17 +4,35 @@ mod layout;
 
 use crate::{
     asset::Asset,
-    doctree::{self, DoctreeItem, Frontispiece, Page},
-    error::Result,
+    config::{self, Config},
+    diagnostics::Diagnostic,
+    diagram::DiagramCache,
+    doctree::{self, DoctreeItem, Frontispiece, OutlineItem, Page},
+    error::{Error, Result},
+    feed::{self, FeedEntry},
+    git,
+    highlight::Highlighter,
+    locale::{Locales, Strings},
+    output::{DirectoryOutput, OutputSink, SubdirectoryOutput},
+    redirect::{self, Redirect},
     search::{self, SearchableDocument},
+    sitemap::{self, SitemapEntry},
+    source::Source,
+    translation, utils,
 };
 use std::{
-    fs::{self, File},
-    io::BufWriter,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
 };
 
-use self::layout::Layout;
+pub use self::layout::Layout;
+
+/// The name of the page written to the root of the site for missing paths
+const NOT_FOUND_FILE_NAME: &str = "404.html";
 
 /// Render Contex
 ///
@@ -25,33 +43,288 @@ use self::layout::Layout;
 /// Nested information about the current output directory, the bale being
 /// rendred, and the current point in the navigation heirachy is stored in the
 /// `RenderState`.
+///
+/// Render contexts are configured with a `RenderContextBuilder`.
 pub struct RenderContext {
-    /// The output directory to write the state to
-    path: PathBuf,
+    /// The output to write the site to
+    output: Rc<dyn OutputSink>,
     /// The overall site name. This is used as the root point in the navigation.
     site_name: String,
     /// The layout for this render
     layout: Option<Box<dyn Layout>>,
+    /// The interface strings for the layout, in every language
+    locales: Locales,
+    /// The documentation source being rendered
+    source: Source,
+    /// Every rendered page, in depth-first reading order
+    reading_order: Vec<PageLink>,
+    /// The navigation tree for the whole site
+    navigation: Vec<NavInfo>,
+    /// Redirects to the pages rendered so far, from their front matter
+    redirects: RefCell<Vec<Redirect>>,
+    /// The redirects written so far, from the root of the output, to list in
+    /// the site's `_redirects` file
+    written_redirects: RefCell<Vec<Redirect>>,
+    /// The pages rendered so far, for the sitemap
+    sitemap: RefCell<Vec<SitemapEntry>>,
+    /// The URL of each sitemap written so far, for `robots.txt`
+    sitemaps: RefCell<Vec<String>>,
+    /// The not found page of the first version and language, to copy to the
+    /// site root
+    root_not_found: RefCell<Option<Vec<u8>>>,
+    /// The versions of the documentation, if several are being built
+    versions: Vec<Variant>,
+    /// The index of the version currently being rendered
+    current_version: Option<usize>,
+    /// The languages of the documentation, if it has been translated
+    languages: Vec<Variant>,
+    /// The index of the language currently being rendered
+    current_language: Option<usize>,
+    /// Fail the render if there are any warnings
+    strict: bool,
 }
 
 impl RenderContext {
-    /// Create a new Root Render Context
+    /// Create a Render Context Builder
     ///
-    /// Root render contexts hold global information about the render, and are
-    /// used as parents for derived cotnexts.
-    pub fn new(path: PathBuf, site_name: String) -> Self {
-        RenderContext {
-            path,
-            site_name,
+    /// The builder configures a render into the given `target` directory. Any
+    /// settings which aren't given use docket's defaults.
+    pub fn builder<P: Into<PathBuf>>(target: P) -> RenderContextBuilder {
+        RenderContextBuilder {
+            output: Box::new(DirectoryOutput::new(target)),
+            site_name: None,
             layout: None,
+            highlighter: None,
+            offline: None,
+            strict: false,
+            excluded: Vec::new(),
+            diagrams: None,
         }
     }
 
+    /// Get the overall site name
+    pub fn site_name(&self) -> &str {
+        &self.site_name
+    }
+
+    /// Get the highlighter used for code blocks
+    pub fn highlighter(&self) -> &dyn Highlighter {
+        self.source.highlighter()
+    }
+
     fn layout(&self) -> &dyn Layout {
         self.layout
             .as_deref()
             .unwrap_or_else(layout::get_default_layout)
     }
+
+    /// Get the output the site is written to
+    fn output(&self) -> &dyn OutputSink {
+        self.output.as_ref()
+    }
+
+    /// Get the site configuration
+    fn config(&self) -> &Config {
+        self.source.config()
+    }
+
+    /// Get the interface strings for the language being rendered
+    pub(crate) fn strings(&self) -> Strings {
+        self.locales.strings(&self.config().lang)
+    }
+
+    /// Get the Path to the Site Being Rendered
+    ///
+    /// When several versions or languages are built each is rendered into its
+    /// own directory, e.g. `v1/ja/`. This is the path to the one being
+    /// rendered now from the root of the output, or empty otherwise.
+    fn prefix(&self) -> String {
+        let version = self.current_version.map(|idx| &self.versions[idx]);
+        let language = self.current_language.map(|idx| &self.languages[idx]);
+        version
+            .into_iter()
+            .chain(language)
+            .map(|variant| format!("{}/", variant.dir))
+            .collect()
+    }
+
+    /// Get the Public URL of the Site Being Rendered
+    ///
+    /// This is the configured `base_url`, followed by the directory of the
+    /// version and language being rendered, if any.
+    pub(crate) fn base_url(&self) -> Option<String> {
+        self.config()
+            .base_url
+            .as_ref()
+            .map(|url| format!("{}/{}", url.trim_end_matches('/'), self.prefix()))
+    }
+
+    /// Get the Absolute Path to the Site Root
+    ///
+    /// This is the path part of the configured `base_url`, e.g. `/docs/`, or
+    /// `/` if no base URL is set. The directory of the version and language
+    /// being rendered is included.
+    fn site_root(&self) -> String {
+        let path = match &self.config().base_url {
+            Some(base_url) => {
+                let host = base_url.find("://").map(|idx| idx + 3).unwrap_or(0);
+                base_url[host..]
+                    .find('/')
+                    .map(|idx| &base_url[host + idx..])
+                    .unwrap_or("/")
+            }
+            None => "/",
+        };
+        let mut root = path.trim_end_matches('/').to_owned();
+        root.push('/');
+        root.push_str(&self.prefix());
+        root
+    }
+}
+
+/// A variant of the documentation, such as a version or a translation, which
+/// is built alongside the others
+struct Variant {
+    /// The directory the variant is rendered to
+    dir: String,
+    /// The name of the variant, as shown to readers
+    name: String,
+    /// The path of each page in the variant, from the variant's directory
+    pages: HashSet<String>,
+}
+
+/// A link to the current page in one variant of the documentation.
+pub(crate) struct SwitcherLink {
+    /// The name of the variant
+    pub name: String,
+    /// Relative URL from the current page to the same page in the variant, or
+    /// to the variant's root if it doesn't have the page
+    pub href: String,
+    /// Is this the variant being rendered
+    pub current: bool,
+}
+
+/// Render Context Builder
+///
+/// Collects the settings for a render. A builder is turned into a
+/// `RenderContext` when it is passed to `Docket::render_with`.
+pub struct RenderContextBuilder {
+    /// The output to write the site to
+    output: Box<dyn OutputSink>,
+    /// The site name, if not the documentation title
+    site_name: Option<String>,
+    /// The layout, if not the default HTML layout
+    layout: Option<Box<dyn Layout>>,
+    /// The highlighter, if not the default highlighter
+    highlighter: Option<Box<dyn Highlighter>>,
+    /// Overrides the configured offline setting
+    offline: Option<bool>,
+    /// Fail the render if there are any warnings
+    strict: bool,
+    /// Paths to leave out of the site
+    excluded: Vec<PathBuf>,
+    /// The diagram cache, if shared with other builds
+    diagrams: Option<DiagramCache>,
+}
+
+impl RenderContextBuilder {
+    /// Set the site name. Defaults to the title of the documentation.
+    pub fn site_name<S: Into<String>>(mut self, site_name: S) -> Self {
+        self.site_name = Some(site_name.into());
+        self
+    }
+
+    /// Set the output the site is written to, in place of the target
+    /// directory. This allows the site to be written to an archive, or
+    /// collected in memory.
+    pub fn output<O: OutputSink + 'static>(mut self, output: O) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Set the layout used to write out each page
+    pub fn layout<L: Layout + 'static>(mut self, layout: L) -> Self {
+        self.layout = Some(Box::new(layout));
+        self
+    }
+
+    /// Set the highlighter used for code blocks
+    pub fn highlighter<H: Highlighter + 'static>(mut self, highlighter: H) -> Self {
+        self.highlighter = Some(Box::new(highlighter));
+        self
+    }
+
+    /// Set whether the site should be built for offline use. Overrides the
+    /// `offline` setting in the site configuration.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = Some(offline);
+        self
+    }
+
+    /// Set whether warnings should be treated as errors. In strict mode a
+    /// render with any warnings fails, and the output isn't finished.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Leave the file or directory at `path` out of the site. This keeps
+    /// output written within the documentation, such as an archive, from
+    /// being copied into the next build as an asset.
+    pub fn exclude<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.excluded.push(path.into());
+        self
+    }
+
+    /// Set the cache of rendered diagrams. Giving each build the same cache
+    /// means diagrams which haven't changed aren't rendered again.
+    pub fn diagram_cache(mut self, diagrams: DiagramCache) -> Self {
+        self.diagrams = Some(diagrams);
+        self
+    }
+
+    /// Build the Render Context
+    ///
+    /// Applies the settings to the given `source`. If no site name was set the
+    /// `title` is used instead.
+    pub(crate) fn build(self, title: String, mut source: Source) -> Result<RenderContext> {
+        if let Some(offline) = self.offline {
+            source.config_mut().offline = offline;
+        }
+        if let Some(highlighter) = self.highlighter {
+            source.set_highlighter(Rc::from(highlighter));
+        }
+        for path in &self.excluded {
+            source.exclude(path);
+        }
+        if let Some(diagrams) = self.diagrams {
+            source.set_diagrams(diagrams);
+        }
+        let layout = match self.layout {
+            Some(layout) => Some(layout),
+            None => layout::load_layout(&source)?,
+        };
+        let locales = Locales::load(&source)?;
+        Ok(RenderContext {
+            output: Rc::from(self.output),
+            site_name: self.site_name.unwrap_or(title),
+            layout,
+            locales,
+            source,
+            reading_order: Vec::new(),
+            navigation: Vec::new(),
+            redirects: RefCell::new(Vec::new()),
+            written_redirects: RefCell::new(Vec::new()),
+            sitemap: RefCell::new(Vec::new()),
+            sitemaps: RefCell::new(Vec::new()),
+            root_not_found: RefCell::new(None),
+            versions: Vec::new(),
+            current_version: None,
+            languages: Vec::new(),
+            current_language: None,
+            strict: self.strict,
+        })
+    }
 }
 
 enum RenderStateKind<'s, 'b> {
@@ -92,8 +365,6 @@ pub struct RenderState<'s, 'b> {
     kind: RenderStateKind<'s, 'b>,
     /// The bale that is being rendered
     bale: &'b Frontispiece,
-    /// The navigation items at this level
-    navs: Vec<NavInfo>,
 }
 
 impl<'s, 'b> RenderState<'s, 'b> {
@@ -101,16 +372,17 @@ impl<'s, 'b> RenderState<'s, 'b> {
     ///
     /// This render state represents the root node in the documentaiton tree. It
     /// renders to the path in the given render context directly.
-    fn new(kind: RenderStateKind<'s, 'b>, bale: &'b Frontispiece, navs: Vec<NavInfo>) -> Self {
-        RenderState { kind, bale, navs }
+    fn new(kind: RenderStateKind<'s, 'b>, bale: &'b Frontispiece) -> Self {
+        RenderState { kind, bale }
     }
 
     /// Get the output path for this render state
     ///
-    /// The path is the folder where items should be created when rendering.
+    /// The path is the folder, relative to the site root, where items should
+    /// be created when rendering.
     fn output_path(&self) -> &Path {
         match &self.kind {
-            RenderStateKind::Root(ctx) => &ctx.path,
+            RenderStateKind::Root(_) => Path::new(""),
             RenderStateKind::Nested(_, path) => path,
         }
     }
@@ -121,7 +393,7 @@ impl<'s, 'b> RenderState<'s, 'b> {
     }
 
     /// Get the render context
-    fn ctx(&self) -> &RenderContext {
+    pub fn ctx(&self) -> &RenderContext {
         match self.kind {
             RenderStateKind::Root(ctx) => ctx,
             RenderStateKind::Nested(parent, _) => parent.ctx(),
@@ -137,8 +409,17 @@ impl<'s, 'b> RenderState<'s, 'b> {
         }
     }
 
-    /// Get the path to the root for the given page kind
-    fn path_to_root(&self, page: &PageKind) -> String {
+    /// Get the Path to the Root
+    ///
+    /// Returns the relative path from the given page back to the site root,
+    /// e.g. `""` or `"../../"`. Links to site-wide assets should start with
+    /// this path. The not found page can be served from any depth, so for it
+    /// the absolute path to the root is returned instead, e.g. `"/"`.
+    pub fn path_to_root(&self, page: &PageKind) -> String {
+        if let PageKind::NotFound = page {
+            return self.ctx().site_root();
+        }
+
         let mut current = self;
         let mut path = String::new();
         while let Some(parent) = current.parent() {
@@ -152,9 +433,125 @@ impl<'s, 'b> RenderState<'s, 'b> {
 
         path
     }
+
+    /// Get the path from the root to the given page kind
+    ///
+    /// This is the inverse of `path_to_root`. The path always has a trailing
+    /// `/`, unless it refers to the root index page in which case it is empty,
+    /// or to the not found page.
+    fn path_from_root(&self, page: &PageKind) -> String {
+        if let PageKind::NotFound = page {
+            return String::from(NOT_FOUND_FILE_NAME);
+        }
+
+        let mut path = String::new();
+        for component in self.output_path().components() {
+            path.push_str(&component.as_os_str().to_string_lossy());
+            path.push('/');
+        }
+
+        if let PageKind::Nested(slug) = page {
+            path.push_str(slug);
+            path.push('/');
+        }
+
+        path
+    }
+
+    /// Get the Path to the Current Bale
+    ///
+    /// Returns the path from the given page to the root of the bale being
+    /// rendered. Links to the bale's other pages start with this path.
+    fn path_to_bale(&self, page: &PageKind) -> String {
+        match page {
+            PageKind::Index(_) => String::from("./"),
+            PageKind::Nested(_) => String::from("../"),
+            PageKind::NotFound => self.path_to_root(page),
+        }
+    }
+
+    /// Get the Links to Each Version
+    ///
+    /// When several versions of the documentation are being built, returns a
+    /// link to the given page in each of them. Versions which don't have the
+    /// page link to the current language's root, or their own root, instead.
+    fn version_links(&self, page: &PageKind) -> Vec<SwitcherLink> {
+        let ctx = self.ctx();
+        let Some(current) = ctx.current_version else {
+            return Vec::new();
+        };
+        let mut root = self.path_to_root(page);
+        let mut language = String::new();
+        if let Some(idx) = ctx.current_language {
+            root.push_str("../");
+            language = format!("{}/", ctx.languages[idx].dir);
+        }
+        let path = format!("{}{}", language, self.path_from_root(page));
+        switcher_links(&root, &ctx.versions, current, &[&path, &language])
+    }
+
+    /// Get the Links to Each Language
+    ///
+    /// When the documentation has been translated, returns a link to the given
+    /// page in each language. Languages without a translation of the page link
+    /// to their root instead.
+    fn language_links(&self, page: &PageKind) -> Vec<SwitcherLink> {
+        let ctx = self.ctx();
+        let Some(current) = ctx.current_language else {
+            return Vec::new();
+        };
+        let root = self.path_to_root(page);
+        let path = self.path_from_root(page);
+        switcher_links(&root, &ctx.languages, current, &[&path])
+    }
+
+    /// Get the Previous and Next Pages
+    ///
+    /// Looks up the given page in the site-wide reading order and returns the
+    /// pages either side of it. Links cross bale boundaries.
+    fn neighbours(&self, page: &PageKind) -> (Option<&PageLink>, Option<&PageLink>) {
+        let order = &self.ctx().reading_order;
+        let path = self.path_from_root(page);
+        match order.iter().position(|link| link.path == path) {
+            Some(idx) => (
+                idx.checked_sub(1).and_then(|prev| order.get(prev)),
+                order.get(idx + 1),
+            ),
+            None => (None, None),
+        }
+    }
+}
+
+/// Link to Each Variant
+///
+/// Builds the links from a page to each of the `variants`, which are rendered
+/// to directories alongside the one at `root`. Each link goes to the first of
+/// the `targets` the variant has a page at, or the variant's root if none.
+fn switcher_links(
+    root: &str,
+    variants: &[Variant],
+    current: usize,
+    targets: &[&str],
+) -> Vec<SwitcherLink> {
+    variants
+        .iter()
+        .enumerate()
+        .map(|(idx, variant)| {
+            let target = targets
+                .iter()
+                .find(|target| variant.pages.contains(**target))
+                .map_or("", |target| target);
+            SwitcherLink {
+                name: variant.name.clone(),
+                href: format!("{}../{}/{}", root, variant.dir, target),
+                current: idx == current,
+            }
+        })
+        .collect()
 }
 
 /// An entry in the navigation tree.
+#[derive(Debug, PartialEq)]
 struct NavInfo {
     /// The title of the item.
     pub title: String,
@@ -182,10 +579,36 @@ impl NavInfo {
     }
 }
 
+/// Build the Navigation Tree
+///
+/// Converts the outline of the doctree into the navigation for the whole
+/// site. Only the first `depth` levels are included; bales at the last level
+/// are kept, but without their contents.
+fn navigation(items: &[OutlineItem], depth: Option<usize>) -> Vec<NavInfo> {
+    items
+        .iter()
+        .map(|item| match item {
+            OutlineItem::Bale { slug, title, items } if depth != Some(1) => {
+                NavInfo::with_children(slug, title, navigation(items, depth.map(|d| d - 1)))
+            }
+            _ => NavInfo::new(item.slug(), item.title()),
+        })
+        .collect()
+}
+
+/// A link to a page in the site-wide reading order.
+#[derive(Debug)]
+pub(crate) struct PageLink {
+    /// Path to the page from the site root (e.g. `guide/intro/`)
+    pub path: String,
+    /// Display title of the page, as HTML
+    pub title: String,
+}
+
 /// A summary of a child page or bale, used to render index cards and sidebar
 /// child-link lists on bale index pages.
 #[derive(Debug)]
-pub(crate) struct CardSummary {
+pub struct CardSummary {
     /// Relative URL from the bale root to the child (e.g. `./slug/`)
     pub href: String,
     /// Display title of the child
@@ -195,26 +618,21 @@ pub(crate) struct CardSummary {
 }
 
 /// Kind of page we are rendering. For index pages we don't need to do anything
-/// to get to the bale root. For nested pages we keep the page's slug.
-pub(crate) enum PageKind {
+/// to get to the bale root. For nested pages we keep the page's slug. More
+/// kinds of page may be added in future.
+#[non_exhaustive]
+pub enum PageKind {
     /// An index page, carrying card summaries for each child item.
     Index(Vec<CardSummary>),
     /// A nested page with a given slug
     Nested(String),
-}
-
-impl PageKind {
-    /// Get the path to the current bale given this page's kind.
-    fn path_to_bale(&self) -> &'static str {
-        match self {
-            PageKind::Index(_) => "./",
-            PageKind::Nested(_) => "../",
-        }
-    }
+    /// The page shown for paths which don't exist in the site. Only rendered
+    /// at the root.
+    NotFound,
 }
 
 enum RenderedItem {
-    Page(Page),
+    Page(Box<Page>),
     Nested(String, Box<RenderedItem>),
 }
 
@@ -243,7 +661,7 @@ impl SearchableDocument for RenderedItem {
 
 impl RenderedItem {
     fn page(page: Page) -> Self {
-        Self::Page(page)
+        Self::Page(Box::new(page))
     }
 
     fn nested(slug: &str, inner: RenderedItem) -> Self {
@@ -266,12 +684,12 @@ fn render_bale_contents(
         state.bale,
         state.output_path()
     );
-    fs::create_dir_all(&state.output_path())?;
 
     let mut rendered_items = Vec::new();
 
     // Always build card summaries for the children when there are items, so
     // both the real index page and the synthetic fallback can show them.
+    let source = &state.ctx().source;
     let cards = build_card_summaries(&items);
     if let Some(page) = state.current_bale().index_page() {
         trace!("Bale has an index. Rendering.");
@@ -284,27 +702,26 @@ fn render_bale_contents(
         let auto = doctree::Page::synthetic(
             title.to_owned(),
             format!("# {}\n\n", title),
+            source.config(),
+            source.highlighter(),
         );
         render_page(&state, PageKind::Index(cards), &auto)?;
     }
 
     // Walk our assets and copy them
     for asset in assets {
-        asset.copy_to(&state.output_path())?;
+        asset.copy_to(source, state.ctx().output(), state.output_path())?;
     }
 
     // Walk the inner items in the bale and render them, in nested contexts if
     // required.
+    let mut feed_entries = Vec::new();
     for item in items {
         match item {
             DoctreeItem::Bale(bale) => {
-                let (bale, assets, items) = bale.break_open()?;
-                let navs = navs_for_items(&items);
-                let state = RenderState::new(
-                    RenderStateKind::with_parent(&state, bale.slug()),
-                    &bale,
-                    navs,
-                );
+                let (bale, assets, items) = bale.into_parts();
+                let state =
+                    RenderState::new(RenderStateKind::with_parent(&state, bale.slug()), &bale);
                 rendered_items.extend(
                     render_bale_contents(&state, assets, items)?
                         .into_iter()
@@ -312,12 +729,48 @@ fn render_bale_contents(
                 );
             }
             DoctreeItem::Page(page) => {
-                render_page(&state, PageKind::Nested(page.slug().to_owned()), &page)?;
+                let kind = PageKind::Nested(page.slug().to_owned());
+                if state.current_bale().is_feed() {
+                    feed_entries.push(FeedEntry {
+                        title: utils::html_to_text(page.title()),
+                        path: state.path_from_root(&kind),
+                        date: page.date(),
+                        authors: page.authors().to_vec(),
+                        summary: page.description(),
+                    });
+                }
+                render_page(&state, kind, &page)?;
                 rendered_items.push(RenderedItem::page(page))
             }
         }
     }
 
+    if state.current_bale().is_feed() {
+        match state.ctx().base_url() {
+            Some(base_url) => feed::write_feed(
+                state.ctx().output(),
+                &base_url,
+                &state.path_from_root(&PageKind::Index(Vec::new())),
+                &utils::html_to_text(state.current_bale().title()),
+                state
+                    .ctx()
+                    .config()
+                    .author
+                    .as_deref()
+                    .unwrap_or(state.ctx().site_name()),
+                SystemTime::now(),
+                feed_entries,
+            )?,
+            None => source.report(
+                Diagnostic::warning(format!(
+                    "the feed for `{}` needs a `base-url` to link to its pages",
+                    state.current_bale().title()
+                ))
+                .in_file(source.root().join(config::CONFIG_FILE_NAME)),
+            ),
+        }
+    }
+
     Ok(rendered_items)
 }
 
@@ -351,27 +804,6 @@ fn build_card_summaries(items: &[DoctreeItem]) -> Vec<CardSummary> {
         .collect()
 }
 
-fn navs_for_items(items: &[DoctreeItem]) -> Vec<NavInfo> {
-    items
-        .iter()
-        .map(|item| match item {
-            DoctreeItem::Page(page) => NavInfo::new(page.slug(), page.title()),
-            DoctreeItem::Bale(bale) => {
-                let children = bale
-                    .peek_children()
-                    .into_iter()
-                    .map(|(slug, title)| NavInfo::new(&slug, &title))
-                    .collect();
-                NavInfo::with_children(
-                    bale.frontispiece().slug(),
-                    bale.frontispiece().title(),
-                    children,
-                )
-            }
-        })
-        .collect()
-}
-
 /// Render a Single Page
 ///
 /// Writes the rendred contents of a given page to a given path.
@@ -379,58 +811,1036 @@ fn render_page(state: &RenderState, kind: PageKind, page: &doctree::Page) -> Res
     let mut path = PathBuf::from(state.output_path());
     if let PageKind::Nested(slug) = &kind {
         path.push(slug);
-        fs::create_dir_all(&path)?;
     };
 
     trace!("rendering page {} at {:?}", page.title(), path);
 
-    let output_path = path.join("index.html");
-    let file = File::create(&output_path)?;
-    let mut writer = BufWriter::new(file);
+    let site_path = state.path_from_root(&kind);
+    if let Some(source_path) = page.source_path() {
+        let declared_in = state.ctx().source.root().join(source_path);
+        state.ctx().redirects.borrow_mut().extend(
+            page.aliases()
+                .iter()
+                .map(|alias| Redirect::new(alias, &site_path, &declared_in)),
+        );
+    }
+    state.ctx().sitemap.borrow_mut().push(SitemapEntry {
+        path: site_path,
+        modified: page.modified(),
+    });
+
+    let mut contents = Vec::new();
+    let layout = state.ctx().layout();
+    layout.render(&mut contents, state, kind, page)?;
+
+    state
+        .ctx()
+        .output()
+        .write(&path.join("index.html"), &contents)?;
+    Ok(())
+}
+
+/// Render the Not Found Page
+///
+/// Writes `404.html` to the root of the site. The content comes from a `404.md`
+/// in the documentation root if there is one, or a default message otherwise.
+/// The page of the first version and language is kept to be copied to the
+/// root of the output.
+fn render_not_found(state: &RenderState) -> Result<()> {
+    let source = &state.ctx().source;
+    let default;
+    let page = match state.current_bale().not_found_page() {
+        Some(page) => page,
+        None => {
+            let strings = state.ctx().strings();
+            let title = strings.get("not-found-title");
+            default = doctree::Page::synthetic(
+                title.to_owned(),
+                format!(
+                    "# {}\n\n{}\n",
+                    title,
+                    strings
+                        .get("not-found-message")
+                        .replace("{home}", &state.path_to_root(&PageKind::NotFound))
+                ),
+                source.config(),
+                source.highlighter(),
+            );
+            &default
+        }
+    };
+
+    trace!("rendering not found page");
 
+    let mut contents = Vec::new();
     let layout = state.ctx().layout();
-    layout.render(&mut writer, state, kind, page)?;
+    layout.render(&mut contents, state, PageKind::NotFound, page)?;
+
+    state
+        .ctx()
+        .output()
+        .write(Path::new(NOT_FOUND_FILE_NAME), &contents)?;
+    if state.ctx().current_version.is_some() || state.ctx().current_language.is_some() {
+        state
+            .ctx()
+            .root_not_found
+            .borrow_mut()
+            .get_or_insert(contents);
+    }
+    Ok(())
+}
+
+/// Build the Reading Order
+///
+/// Walks the outline of the doctree, `items`, depth-first to list every page
+/// that will be rendered. Each bale's index page comes before the bale's
+/// contents.
+fn reading_order(root: &doctree::OpenBale, items: &[OutlineItem]) -> Vec<PageLink> {
+    fn visit(prefix: &str, items: &[OutlineItem], order: &mut Vec<PageLink>) {
+        for item in items {
+            let path = format!("{}{}/", prefix, item.slug());
+            order.push(PageLink {
+                path: path.clone(),
+                title: item.title().to_owned(),
+            });
+            if let OutlineItem::Bale { items, .. } = item {
+                visit(&path, items, order);
+            }
+        }
+    }
+
+    let mut order = Vec::new();
+    // The root only has an index page if it has content to render.
+    if root.frontispiece().index_page().is_some() || !items.is_empty() {
+        order.push(PageLink {
+            path: String::new(),
+            title: root.frontispiece().title().to_owned(),
+        });
+    }
+    visit("", items, &mut order);
+    order
+}
+
+/// Gather the Redirects
+///
+/// Combines the redirects from page front matter with those from the site
+/// configuration. Redirects which lead outside the site, or would replace a
+/// page or an earlier redirect, are reported and skipped.
+fn collect_redirects(ctx: &RenderContext) -> Vec<Redirect> {
+    let config_path = ctx.source.root().join(config::CONFIG_FILE_NAME);
+    let mut redirects = ctx.redirects.take();
+    redirects.extend(
+        ctx.config()
+            .redirects
+            .iter()
+            .map(|(from, to)| Redirect::new(from, to, &config_path)),
+    );
 
+    let pages: HashSet<_> = ctx
+        .reading_order
+        .iter()
+        .map(|link| &link.path[..])
+        .collect();
+    let mut seen: HashMap<String, PathBuf> = HashMap::new();
+    redirects.retain(|redirect| {
+        let problem = if let Some(path) = redirect.outside_site() {
+            format!("redirect path `{}` is outside the site", path)
+        } else if pages.contains(redirect.from()) {
+            format!("redirect from `{}` would replace a page", redirect.from())
+        } else if let Some(first) = seen.get(redirect.from()) {
+            format!(
+                "duplicate redirect from `{}`, also declared in {}",
+                redirect.from(),
+                first.display()
+            )
+        } else {
+            seen.insert(
+                redirect.from().to_owned(),
+                redirect.declared_in().to_owned(),
+            );
+            return true;
+        };
+        ctx.source
+            .report(Diagnostic::warning(problem).in_file(redirect.declared_in()));
+        false
+    });
+    redirects
+}
+
+/// Write Redirects
+///
+/// Writes a stub for each of the `redirects` to the context's output. They
+/// are kept to be listed in the `_redirects` file at the root of the site
+/// once every version and language has been rendered.
+fn write_redirects(ctx: &RenderContext, redirects: &[Redirect]) -> Result<()> {
+    redirect::write_stubs(ctx.output(), redirects)?;
+    let prefix = ctx.prefix();
+    ctx.written_redirects.borrow_mut().extend(
+        redirects
+            .iter()
+            .map(|redirect| redirect.with_prefix(&prefix)),
+    );
     Ok(())
 }
 
 /// Copy any assets used by the layout
 fn copy_global_assets(ctx: &RenderContext) -> Result<()> {
-    fs::create_dir_all(&ctx.path)?;
+    let source = &ctx.source;
+    let root = Path::new("");
     for asset in ctx.layout().assets()? {
-        asset.copy_to(&ctx.path)?;
+        asset.copy_to(source, ctx.output(), root)?;
+    }
+    for asset in ctx.highlighter().assets()? {
+        asset.copy_to(source, ctx.output(), root)?;
+    }
+
+    // Extra stylesheets and scripts keep their place relative to the source
+    // root, so every page can link to them in the same way.
+    for extra in source.extra_css().iter().chain(source.extra_js()) {
+        let site_path = source.extra_site_path(extra);
+        let dir = site_path.parent().unwrap_or(root);
+        Asset::path(extra.clone()).copy_to(source, ctx.output(), dir)?;
     }
 
     Ok(())
 }
 
-/// Render a Doctree
+/// Render a Site
 ///
-/// Wwrite  the given doctree out to the `target` path using the default render
-/// contex.
-pub(crate) fn render<P: AsRef<Path>>(
-    target: P,
-    title: String,
-    doctree_root: doctree::Bale,
-) -> Result<()> {
-    // The render context. This contains the global state used in rendering
-    let ctx = RenderContext::new(target.as_ref().to_owned(), title);
-
-    // Break opne the root bale and build a root render state. This is the root
+/// Renders the given doctree, read from the context's source, to the context's
+/// output. The context's reading order must already have been built.
+fn render_site(ctx: &RenderContext, doctree_root: doctree::OpenBale) -> Result<()> {
+    // Split up the root bale and build a root render state. This is the root
     // of the render state tree as we walk the document tree.
-    let (frontispiece, assets, items) = doctree_root.break_open()?;
-    let navs = navs_for_items(&items);
-    let state = RenderState::new(RenderStateKind::new_root(&ctx), &frontispiece, navs);
+    let (frontispiece, assets, items) = doctree_root.into_parts();
+    let state = RenderState::new(RenderStateKind::new_root(ctx), &frontispiece);
 
     // Copy any global assets. This allows layouts and other global items to
     // include items in the output.
-    copy_global_assets(&ctx)?;
+    copy_global_assets(ctx)?;
 
     // Render the documentation itself.
     let docs = render_bale_contents(&state, assets, items)?;
+    render_not_found(&state)?;
 
     // Write out a search index for all the rendered documents.
-    search::write_search_indices(&ctx.path, docs.iter())?;
+    search::write_search_indices(ctx.output(), docs.iter())?;
 
+    // Write stubs at the old paths of any moved pages.
+    write_redirects(ctx, &collect_redirects(ctx))?;
+
+    // List every rendered page for search engines, if we know where the site
+    // will be hosted.
+    if let Some(base_url) = ctx.base_url() {
+        sitemap::write_sitemap(ctx.output(), &base_url, &ctx.sitemap.take())?;
+        ctx.sitemaps.borrow_mut().push(format!(
+            "{}{}",
+            sitemap::absolute_url(&base_url, ""),
+            sitemap::SITEMAP_FILE_NAME
+        ));
+    }
+
+    Ok(())
+}
+
+/// Render the Doctree
+///
+/// Builds the navigation for the given doctree, opened from the context's
+/// source in the language being read, and renders it to the context's output.
+fn render_tree(ctx: &mut RenderContext, doctree_root: doctree::OpenBale) -> Result<()> {
+    let outline = doctree_root.outline();
+    ctx.reading_order = reading_order(&doctree_root, &outline);
+    let depth = ctx.config().nav_depth.map(NonZeroUsize::get);
+    ctx.navigation = navigation(&outline, depth);
+    render_site(ctx, doctree_root)
+}
+
+/// Open the Doctree in Each Language
+///
+/// Opens the doctree in each of the source's languages, and lists which pages
+/// have been translated into each. If no languages are configured the list of
+/// languages is empty and the doctree is opened just once.
+fn open_languages(source: &mut Source) -> Result<(Vec<Variant>, Vec<doctree::OpenBale>)> {
+    let languages = translation::languages(source);
+    if languages.is_empty() {
+        return Ok((Vec::new(), vec![doctree::open(source.root(), source)?]));
+    }
+
+    let mut variants = Vec::with_capacity(languages.len());
+    let mut trees = Vec::with_capacity(languages.len());
+    for (idx, language) in languages.into_iter().enumerate() {
+        source.set_translation((idx > 0).then_some(&language.code[..]));
+        let doctree_root = doctree::open(source.root(), source)?;
+        variants.push(Variant {
+            pages: reading_order(&doctree_root, &doctree_root.outline())
+                .into_iter()
+                .map(|link| link.path)
+                .collect(),
+            dir: language.code,
+            name: language.name,
+        });
+        trees.push(doctree_root);
+    }
+    source.set_translation(None);
+    Ok((variants, trees))
+}
+
+/// Render Each Language
+///
+/// Renders the doctree opened in each of the context's languages, `trees`,
+/// into its own subdirectory of the output. The root of the output redirects
+/// to the default language. If the documentation hasn't been translated its
+/// one doctree is rendered to the output directly.
+fn render_languages(ctx: &mut RenderContext, trees: Vec<doctree::OpenBale>) -> Result<()> {
+    let Some(default) = ctx.languages.first() else {
+        return trees
+            .into_iter()
+            .try_for_each(|doctree_root| render_tree(ctx, doctree_root));
+    };
+    let home = Redirect::new(
+        "",
+        &default.dir,
+        ctx.source.root().join(config::CONFIG_FILE_NAME),
+    );
+
+    let parent = Rc::clone(&ctx.output);
+    for (idx, doctree_root) in trees.into_iter().enumerate() {
+        let code = ctx.languages[idx].dir.clone();
+        ctx.source.set_translation((idx > 0).then_some(&code[..]));
+        ctx.current_language = Some(idx);
+        ctx.output = Rc::new(SubdirectoryOutput::new(Rc::clone(&parent), &code));
+        render_tree(ctx, doctree_root)?;
+    }
+    ctx.source.set_translation(None);
+    ctx.current_language = None;
+    ctx.output = parent;
+
+    write_redirects(ctx, &[home])?;
     Ok(())
 }
+
+/// Render Each Version
+///
+/// Reads each configured version of the documentation from git and renders it
+/// into its own subdirectory of the output. Every version uses the layout and
+/// site-wide settings, such as the `base-url`, from the working tree. The root
+/// of the site redirects to the first version. Returns the warnings found in
+/// all of the versions.
+fn render_versions(ctx: &mut RenderContext) -> Result<Vec<Diagnostic>> {
+    let root = ctx.source.root().to_owned();
+    let config_path = root.join(config::CONFIG_FILE_NAME);
+    let configs = ctx.config().versions.clone();
+
+    // Open every version up front, so each knows which pages the others have.
+    let mut versions = Vec::with_capacity(configs.len());
+    ctx.versions = Vec::with_capacity(configs.len());
+    for version in &configs {
+        trace!("Reading version {} from {}", version.name, version.git_ref);
+        let fs = git::read_tree(&root, &version.git_ref).map_err(|e| {
+            Error::InvalidConfig(
+                config_path.clone(),
+                format!(
+                    "could not read version `{}` from `{}`: {}",
+                    version.name, version.git_ref, e
+                ),
+            )
+        })?;
+        let mut source = Source::open_at(&root, Box::new(fs), Some(&version.git_ref))?;
+        if let Some(highlighter) = ctx.source.custom_highlighter() {
+            source.set_highlighter(highlighter);
+        }
+        source.set_diagrams(ctx.source.diagrams().clone());
+        let site = ctx.config();
+        let config = source.config_mut();
+        config.base_url = site.base_url.clone();
+        config.offline = site.offline;
+        let (languages, trees) = open_languages(&mut source)?;
+        let pages = if languages.is_empty() {
+            trees
+                .iter()
+                .flat_map(|doctree_root| reading_order(doctree_root, &doctree_root.outline()))
+                .map(|link| link.path)
+                .collect()
+        } else {
+            languages
+                .iter()
+                .flat_map(|language| {
+                    language
+                        .pages
+                        .iter()
+                        .map(move |path| format!("{}/{}", language.dir, path))
+                })
+                .collect()
+        };
+        ctx.versions.push(Variant {
+            dir: version.name.clone(),
+            name: version.name.clone(),
+            pages,
+        });
+        versions.push((source, languages, trees));
+    }
+
+    let mut diagnostics = ctx.source.take_diagnostics();
+    let shared = Rc::clone(&ctx.output);
+    let mut working = None;
+    for (idx, (source, languages, trees)) in versions.into_iter().enumerate() {
+        working.get_or_insert(std::mem::replace(&mut ctx.source, source));
+        ctx.languages = languages;
+        ctx.current_version = Some(idx);
+        ctx.output = Rc::new(SubdirectoryOutput::new(
+            Rc::clone(&shared),
+            &configs[idx].name,
+        ));
+        render_languages(ctx, trees)?;
+        diagnostics.extend(ctx.source.take_diagnostics());
+    }
+    if let Some(working) = working {
+        ctx.source = working;
+    }
+    ctx.output = shared;
+    ctx.current_version = None;
+    ctx.languages = Vec::new();
+
+    if let Some(first) = configs.first() {
+        let home = Redirect::new("", &first.name, &config_path);
+        write_redirects(ctx, &[home])?;
+    }
+
+    Ok(diagnostics)
+}
+
+/// Render a Doctree
+///
+/// Opens the doctree from the source in the given render context and writes
+/// it out to the context's output. If versions or languages are configured
+/// each of them is rendered instead. Returns the warnings found while
+/// rendering.
+pub(crate) fn render(mut ctx: RenderContext) -> Result<Vec<Diagnostic>> {
+    trace!("Rendering documentation for {}", ctx.site_name);
+    let mut diagnostics = if ctx.config().versions.is_empty() {
+        let (languages, trees) = open_languages(&mut ctx.source)?;
+        ctx.languages = languages;
+        render_languages(&mut ctx, trees)?;
+        ctx.source.take_diagnostics()
+    } else {
+        render_versions(&mut ctx)?
+    };
+
+    // Hosts look for the not found page at the root of the site.
+    if let Some(not_found) = ctx.root_not_found.take() {
+        ctx.output()
+            .write(Path::new(NOT_FOUND_FILE_NAME), &not_found)?;
+    }
+
+    // Hosts only read the `_redirects` file at the root of the site.
+    redirect::write_listing(ctx.output(), &ctx.written_redirects.take())?;
+
+    // Point search engines at the sitemap of each version and language.
+    if ctx.config().robots_txt {
+        if ctx.config().base_url.is_some() {
+            sitemap::write_robots(ctx.output(), &ctx.sitemaps.take())?;
+        } else {
+            diagnostics.push(
+                Diagnostic::warning("`robots-txt` needs a `base-url` to link to the sitemap")
+                    .in_file(ctx.source.root().join(config::CONFIG_FILE_NAME)),
+            );
+        }
+    }
+
+    if ctx.strict && !diagnostics.is_empty() {
+        return Err(Error::Strict(diagnostics));
+    }
+
+    ctx.output().finish()?;
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        docket::Docket,
+        output::MemoryOutput,
+        source::{MemoryFs, Metadata, SourceFs},
+    };
+
+    /// Open the documentation in `docs/` made up of `files`, with a
+    /// `docket.toml` holding `config`
+    fn docs(config: &str, files: &[(&str, &str)]) -> Docket {
+        Docket::open_with("docs", MemoryFs::with_config(config, files)).unwrap()
+    }
+
+    /// Render `docket` into memory, returning the output and any warnings
+    fn render(docket: Docket) -> (MemoryOutput, Vec<Diagnostic>) {
+        let output = MemoryOutput::new();
+        let warnings = docket
+            .render_with(RenderContext::builder("build").output(output.clone()))
+            .unwrap();
+        (output, warnings)
+    }
+
+    /// Get the rendered contents of `path` from the `output`
+    fn page(output: &MemoryOutput, path: &str) -> String {
+        String::from_utf8(output.get(path).unwrap()).unwrap()
+    }
+
+    fn example() -> Docket {
+        docs(
+            "",
+            &[
+                ("docs/index.md", "# Home\n"),
+                ("docs/intro.md", "No heading\n"),
+            ],
+        )
+    }
+
+    #[test]
+    fn render_returns_warnings() {
+        let (output, warnings) = render(example());
+        assert_eq!(1, warnings.len());
+        assert_eq!(Some(Path::new("docs/intro.md")), warnings[0].path());
+        assert!(output.get("intro/index.html").is_some());
+    }
+
+    #[test]
+    fn render_page_nav_titles() {
+        let (output, _) = render(docs(
+            "",
+            &[
+                ("docs/index.md", "# Home\n"),
+                ("docs/<b> & co.md", "No heading\n"),
+                ("docs/intro.md", "# Intro\n"),
+            ],
+        ));
+
+        let intro = page(&output, "intro/index.html");
+        assert!(intro.contains("<span class='page-nav-title'>&lt;b&gt; &amp; co</span>"));
+    }
+
+    #[test]
+    fn render_redirects() {
+        let (output, warnings) = render(docs(
+            "[redirects]\n\"old/faq\" = \"guide/\"\n\"install\" = \"/\"\n",
+            &[
+                ("docs/index.md", "# Home\n"),
+                ("docs/guide/index.md", "# Guide\n"),
+                (
+                    "docs/guide/setup.md",
+                    "+++\naliases = [\"install/\", \"guide/\", \"../../etc/x\"]\n+++\n# Setup\n",
+                ),
+            ],
+        ));
+
+        let warnings: Vec<_> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            vec![
+                "docs/guide/setup.md: warning: redirect from `guide/` would replace a page",
+                "docs/guide/setup.md: warning: redirect path `../../etc/x/` is outside the site",
+                "docs/docket.toml: warning: duplicate redirect from `install/`, also declared in docs/guide/setup.md",
+            ],
+            warnings
+        );
+        assert!(page(&output, "install/index.html").contains("url=../guide/setup/"));
+        assert!(output.get("old/faq/index.html").is_some());
+        assert!(output.files().keys().all(|path| !path.starts_with("..")));
+        assert_eq!(
+            "/install/ /guide/setup/ 301\n/old/faq/ /guide/ 301\n",
+            page(&output, "_redirects")
+        );
+        assert!(!page(&output, "guide/setup/index.html").contains("aliases"));
+    }
+
+    #[test]
+    fn render_sitemap() {
+        let (output, _) = render(docs(
+            "base-url = \"https://example.com/docs\"\nrobots-txt = true\n",
+            &[
+                ("docs/index.md", "# Home\n"),
+                ("docs/guide/index.md", "# Guide\n"),
+                (
+                    "docs/guide/setup.md",
+                    "+++\naliases = [\"install/\"]\n+++\n# Setup\n",
+                ),
+            ],
+        ));
+
+        let sitemap = page(&output, "sitemap.xml");
+        let urls: Vec<_> = sitemap
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("<loc>"))
+            .collect();
+        assert_eq!(
+            vec![
+                "https://example.com/docs/</loc>",
+                "https://example.com/docs/guide/</loc>",
+                "https://example.com/docs/guide/setup/</loc>",
+            ],
+            urls
+        );
+        assert!(output.get("robots.txt").is_some());
+    }
+
+    #[test]
+    fn render_page_metadata() {
+        let (output, _) = render(docs(
+            "base-url = \"https://example.com/\"\nlang = \"en-GB\"\n",
+            &[
+                ("docs/index.md", "# Home\n\nThe *home* page.\n"),
+                (
+                    "docs/intro.md",
+                    "+++\ndescription = \"All about it.\"\n+++\n# Intro\n",
+                ),
+            ],
+        ));
+
+        let index = page(&output, "index.html");
+        assert!(index.contains("<html lang=\"en-GB\">"));
+        assert!(index.contains("<meta name=\"description\" content=\"The home page.\">"));
+        assert!(index.contains("<link rel=\"canonical\" href=\"https://example.com/\">"));
+        assert!(index.contains("<meta property=\"og:type\" content=\"website\">"));
+
+        let intro = page(&output, "intro/index.html");
+        assert!(intro.contains("<meta property=\"og:title\" content=\"Intro\">"));
+        assert!(intro.contains("<meta property=\"og:type\" content=\"article\">"));
+        assert!(intro.contains("<meta name=\"twitter:description\" content=\"All about it.\">"));
+        assert!(intro.contains("<meta property=\"og:url\" content=\"https://example.com/intro/\">"));
+    }
+
+    #[test]
+    fn render_extra_assets() {
+        let (output, _) = render(docs(
+            "extra-css = [\"theme/a/theme.css\", \"theme/b/theme.css\"]\n\
+             extra-js = [\"theme/site.js\"]\n",
+            &[
+                ("docs/index.md", "# Home\n"),
+                ("docs/guide.md", "# Guide\n"),
+                ("docs/theme/a/theme.css", "@import \"../base.css\";\n"),
+                ("docs/theme/b/theme.css", "body {}\n"),
+                ("docs/theme/site.js", "\n"),
+            ],
+        ));
+
+        assert_eq!(
+            "@import \"../base.css\";\n",
+            page(&output, "theme/a/theme.css")
+        );
+        assert_eq!("body {}\n", page(&output, "theme/b/theme.css"));
+        let guide = page(&output, "guide/index.html");
+        assert!(guide.contains("<link rel=\"stylesheet\" href=\"../theme/a/theme.css\">"));
+        assert!(guide.contains("<link rel=\"stylesheet\" href=\"../theme/b/theme.css\">"));
+        assert!(guide.contains("<script src=\"../theme/site.js\" defer></script>"));
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn render_archive_within_source() {
+        use crate::output::{ArchiveFormat, ArchiveOutput};
+
+        let dir = std::env::temp_dir().join(format!("docket-archive-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.md"), "# Home\n").unwrap();
+        let archive = dir.join("site.zip");
+
+        // The second build finds the archive from the first in the source.
+        for _ in 0..2 {
+            let builder = RenderContext::builder("build")
+                .exclude(&archive)
+                .output(ArchiveOutput::new(&archive, ArchiveFormat::Zip));
+            Docket::open(&dir).unwrap().render_with(builder).unwrap();
+        }
+        let zip = zip::ZipArchive::new(std::fs::File::open(&archive).unwrap()).unwrap();
+        let names: Vec<_> = zip.file_names().collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(names.contains(&"index.html"));
+        assert!(!names.contains(&"site.zip"));
+    }
+
+    const NEWS: &[(&str, &str)] = &[
+        ("docs/index.md", "# Home\n"),
+        ("docs/news/index.md", "# News\n"),
+        (
+            "docs/news/v1.md",
+            "+++\ndate = 2024-01-10\nauthors = [\"Ann\"]\n+++\n# Version 1\n\nThe first release.\n",
+        ),
+        (
+            "docs/news/v2.md",
+            "+++\ndate = 2024-06-01\n+++\n# Version 2\n\nThe second release.\n",
+        ),
+    ];
+
+    #[test]
+    fn render_feed() {
+        let config =
+            "base-url = \"https://example.com/\"\nfeeds = [\"news\"]\nauthor = \"The Team\"\n";
+        let (output, warnings) = render(docs(config, NEWS));
+        assert!(warnings.is_empty());
+
+        let feed = page(&output, "news/feed.xml");
+        assert!(feed.contains("<title>News</title>"));
+        assert!(feed.contains("<updated>2024-06-01T00:00:00Z</updated>"));
+        assert!(feed.contains("<summary>The first release.</summary>"));
+        assert!(feed.contains("  <author><name>The Team</name></author>\n"));
+        assert!(feed.contains("    <author><name>Ann</name></author>\n"));
+        let v1 = feed.find("https://example.com/news/v1/").unwrap();
+        let v2 = feed.find("https://example.com/news/v2/").unwrap();
+        assert!(v2 < v1);
+
+        let link = "<link rel=\"alternate\" type=\"application/atom+xml\" title=\"News\"";
+        let index = page(&output, "news/index.html");
+        assert!(index.contains(&format!("{} href=\"./feed.xml\">", link)));
+        let v1 = page(&output, "news/v1/index.html");
+        assert!(v1.contains(&format!("{} href=\"../feed.xml\">", link)));
+        assert!(!page(&output, "index.html").contains(link));
+    }
+
+    #[test]
+    fn feed_needs_base_url() {
+        let (output, warnings) = render(docs("feeds = [\"news/\"]\n", NEWS));
+        assert_eq!(1, warnings.len());
+        assert_eq!(
+            "the feed for `News` needs a `base-url` to link to its pages",
+            warnings[0].message()
+        );
+        assert!(output.get("news/feed.xml").is_none());
+    }
+
+    #[test]
+    fn render_default_not_found_page() {
+        let (output, _) = render(example());
+
+        let not_found = page(&output, "404.html");
+        assert!(not_found.contains("<h1 id='page-not-found'>Page Not Found"));
+        assert!(not_found.contains("<a href=\"/\">home page</a>"));
+        assert!(not_found.contains("<link rel=\"stylesheet\" href=\"/style.css\">"));
+        assert!(not_found.contains("<script src=\"/search.js\" type=module></script>"));
+        assert!(not_found.contains("<a href='/intro/'>"));
+        assert!(!not_found.contains("rel='next'"));
+    }
+
+    #[test]
+    fn render_custom_not_found_page() {
+        let (output, _) = render(docs(
+            "base-url = \"https://example.com/docs\"\n",
+            &[
+                ("docs/index.md", "# Home\n"),
+                ("docs/404.md", "# Lost\n\nNothing to see.\n"),
+                ("docs/guide/404.md", "# Not Special\n"),
+            ],
+        ));
+
+        let not_found = page(&output, "404.html");
+        assert!(not_found.contains("<h1 id='lost'>Lost"));
+        assert!(not_found.contains("<link rel=\"stylesheet\" href=\"/docs/style.css\">"));
+        assert!(!not_found.contains("rel=\"canonical\""));
+        assert!(output.get("404/index.html").is_none());
+        assert!(output.get("guide/404/index.html").is_some());
+    }
+
+    const DEEP_TREE: &[(&str, &str)] = &[
+        ("docs/index.md", "# Home\n"),
+        ("docs/01-setup.md", "# Setup\n"),
+        ("docs/02-guide/index.md", "# Guide\n"),
+        ("docs/02-guide/basics.md", "# Basics\n"),
+        ("docs/02-guide/advanced/tuning.md", "# Tuning\n"),
+    ];
+
+    #[test]
+    fn render_deep_navigation() {
+        let (output, _) = render(docs("", DEEP_TREE));
+
+        let home = page(&output, "index.html");
+        assert!(home.contains("<a href='guide/advanced/tuning/'>Tuning</a>"));
+        assert!(!home.contains("<details open>"));
+
+        let tuning = page(&output, "guide/advanced/tuning/index.html");
+        assert!(tuning.contains("<a href='../../../setup/'>Setup</a>"));
+        assert!(tuning.contains("<a href='../../../guide/basics/'>Basics</a>"));
+        assert!(tuning
+            .contains("<a href='../../../guide/advanced/tuning/' aria-current='page'>Tuning</a>"));
+        assert_eq!(2, tuning.matches("<details open>").count());
+
+        let guide = page(&output, "guide/index.html");
+        assert!(guide.contains("<a href='../guide/' aria-current='page'>Guide</a>"));
+        assert_eq!(1, guide.matches("<details open>").count());
+    }
+
+    #[test]
+    fn limit_navigation_depth() {
+        let (output, _) = render(docs("nav-depth = 2\n", DEEP_TREE));
+
+        let home = page(&output, "index.html");
+        assert!(home.contains("<li><a href='guide/advanced/'>advanced</a></li>"));
+        assert!(!home.contains("Tuning"));
+
+        let items = [OutlineItem::Bale {
+            slug: String::from("guide"),
+            title: String::from("Guide"),
+            items: vec![OutlineItem::Page {
+                slug: String::from("basics"),
+                title: String::from("Basics"),
+            }],
+        }];
+        assert_eq!(
+            vec![NavInfo::new("guide", "Guide")],
+            navigation(&items, Some(1))
+        );
+        assert_eq!(
+            vec![NavInfo::with_children(
+                "guide",
+                "Guide",
+                vec![NavInfo::new("basics", "Basics")]
+            )],
+            navigation(&items, None)
+        );
+    }
+
+    #[test]
+    fn render_localised_strings() {
+        let (output, _) = render(docs(
+            "lang = \"fr\"\n",
+            &[
+                ("docs/index.md", "# Accueil\n"),
+                ("docs/guide.md", "# Guide\n\n## Installer\n"),
+                ("docs/strings.toml", "[fr]\nsearch = \"Chercher\"\n"),
+            ],
+        ));
+
+        let guide = page(&output, "guide/index.html");
+        assert!(guide.contains("<h2>Sur cette page</h2>"));
+        assert!(guide.contains("aria-label=\"Afficher ou masquer la navigation\""));
+        assert!(guide.contains("aria-label='Lien permanent vers ce titre'"));
+        assert!(guide.contains("<span class='page-nav-label'>Précédent</span>"));
+        assert!(guide.contains("data-search=\"Chercher\""));
+        assert!(guide.contains(
+            "<p>Généré par <a href='https://github.com/iwillspeak/docket/'>Docket</a></p>"
+        ));
+        assert!(!guide.contains("{{"));
+        let not_found = page(&output, "404.html");
+        assert!(not_found.contains("<h1 id='page-introuvable'>Page introuvable"));
+        assert!(not_found.contains("<a href=\"/\">page d’accueil</a>"));
+        assert!(output.get("strings.toml").is_none());
+    }
+
+    /// Run `git` within `dir`, for building a test repository
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=Docket"])
+            .args(["-c", "user.email=docket@example.com"])
+            .args(["-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn render_versions() {
+        let dir = std::env::temp_dir().join(format!("docket-versions-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let docs = dir.join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        git(&dir, &["init", "-q"]);
+        std::fs::write(docs.join("index.md"), "# Home\n").unwrap();
+        std::fs::write(
+            docs.join("setup.md"),
+            "+++\naliases = [\"getting-started\"]\n+++\n# Setup\n",
+        )
+        .unwrap();
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-q", "-m", "Version one"]);
+        git(&dir, &["tag", "v1.0"]);
+        std::fs::remove_file(docs.join("setup.md")).unwrap();
+        std::fs::write(docs.join("install.md"), "# Install\n").unwrap();
+        std::fs::write(
+            docs.join("docket.toml"),
+            "[[versions]]\nname = \"latest\"\nref = \"HEAD\"\n\n\
+             [[versions]]\nname = \"v1\"\nref = \"v1.0\"\n",
+        )
+        .unwrap();
+        git(&dir, &["add", "-A"]);
+        git(&dir, &["commit", "-q", "-m", "Version two"]);
+        // Uncommitted pages aren't part of any version.
+        std::fs::write(docs.join("draft.md"), "# Draft\n").unwrap();
+
+        let (output, _) = render(Docket::open(&docs).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(output.get("latest/install/index.html").is_some());
+        assert!(output.get("latest/draft/index.html").is_none());
+        assert!(output.get("v1/setup/index.html").is_some());
+        assert!(page(&output, "index.html").contains("url=latest/"));
+        assert!(page(&output, "v1/getting-started/index.html").contains("url=../setup/"));
+        assert!(output.get("v1/_redirects").is_none());
+        assert_eq!(
+            "/v1/getting-started/ /v1/setup/ 301\n/ /latest/ 301\n",
+            page(&output, "_redirects")
+        );
+
+        let setup = page(&output, "v1/setup/index.html");
+        assert!(setup.contains("<summary>v1</summary>"));
+        assert!(setup.contains("<li><a href='../../latest/'>latest</a></li>"));
+        assert!(setup.contains("<li class='current'><a href='../../v1/setup/'>v1</a></li>"));
+        let index = page(&output, "latest/index.html");
+        assert!(index.contains("<li><a href='../v1/'>v1</a></li>"));
+        let not_found = page(&output, "latest/404.html");
+        assert!(not_found.contains("<link rel=\"stylesheet\" href=\"/latest/style.css\">"));
+        assert_eq!(not_found, page(&output, "404.html"));
+    }
+
+    const TRANSLATED: &[(&str, &str)] = &[
+        ("docs/index.md", "# Home\n"),
+        ("docs/index.ja.md", "# ホーム\n"),
+        ("docs/setup.md", "# Setup\n"),
+        ("docs/ja/setup.md", "# セットアップ\n"),
+        ("docs/usage.md", "# Usage\n"),
+    ];
+
+    /// Open the translated documentation, with `config` added to the settings
+    fn translated(config: &str) -> Docket {
+        let config = format!(
+            "base-url = \"https://example.com/\"\nrobots-txt = true\n{}\n\
+             [languages]\nen = \"English\"\nja = \"日本語\"\n",
+            config
+        );
+        docs(&config, TRANSLATED)
+    }
+
+    #[test]
+    fn render_translations() {
+        let (output, warnings) = render(translated("[redirects]\n\"old-setup\" = \"setup/\"\n"));
+        assert_eq!(1, warnings.len());
+        assert_eq!("missing `ja` translation", warnings[0].message());
+        assert_eq!(Some(Path::new("docs/usage.md")), warnings[0].path());
+
+        assert!(output.get("en/usage/index.html").is_some());
+        assert!(output.get("ja/usage/index.html").is_none());
+        assert!(output.get("en/search_index.json").is_some());
+        assert!(output.get("ja/search_index.json").is_some());
+        assert!(page(&output, "index.html").contains("url=en/"));
+        assert_eq!(page(&output, "en/404.html"), page(&output, "404.html"));
+        assert!(page(&output, "ja/old-setup/index.html").contains("url=../setup/"));
+        assert!(output.get("en/_redirects").is_none());
+        assert_eq!(
+            "/en/old-setup/ /en/setup/ 301\n/ja/old-setup/ /ja/setup/ 301\n/ /en/ 301\n",
+            page(&output, "_redirects")
+        );
+
+        let setup = page(&output, "ja/setup/index.html");
+        assert!(setup.contains("<html lang=\"ja\">"));
+        assert!(setup.contains("<h1 id='セットアップ'>"));
+        assert!(setup.contains("<summary>日本語</summary>"));
+        assert!(setup.contains("<li><a href='../../en/setup/'>English</a></li>"));
+        assert!(setup.contains("href=\"https://example.com/ja/setup/\""));
+        let usage = page(&output, "en/usage/index.html");
+        assert!(usage.contains("<li><a href='../../ja/'>日本語</a></li>"));
+        assert_eq!(
+            "User-agent: *\nAllow: /\n\nSitemap: https://example.com/en/sitemap.xml\n\
+             Sitemap: https://example.com/ja/sitemap.xml\n",
+            page(&output, "robots.txt")
+        );
+    }
+
+    #[test]
+    fn render_translation_fallback() {
+        let (output, warnings) = render(translated("translation-fallback = true"));
+        assert_eq!(1, warnings.len());
+        let usage = page(&output, "ja/usage/index.html");
+        assert!(usage.contains("<h1 id='usage'>Usage"));
+        assert!(usage.contains("<li><a href='../../en/usage/'>English</a></li>"));
+    }
+
+    /// A file system which counts how many times each file is read
+    struct CountingFs(MemoryFs, Rc<RefCell<HashMap<PathBuf, usize>>>);
+
+    impl SourceFs for CountingFs {
+        fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+            self.0.read_dir(path)
+        }
+
+        fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+            *self.1.borrow_mut().entry(path.to_owned()).or_default() += 1;
+            self.0.read(path)
+        }
+
+        fn metadata(&self, path: &Path) -> std::io::Result<Metadata> {
+            self.0.metadata(path)
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn render_reuses_diagram_cache() {
+        // Each run of the command adds the diagram to the log.
+        let log = std::env::temp_dir().join(format!("docket-diagrams-{}", std::process::id()));
+        let _ = std::fs::remove_file(&log);
+        let config = format!("[diagrams]\ndot = \"tee -a {}\"\n", log.display());
+        let files = &[("docs/index.md", "# Home\n\n```dot\n<svg></svg>\n```\n")];
+
+        let cache = DiagramCache::new();
+        for _ in 0..2 {
+            let output = MemoryOutput::new();
+            let builder = RenderContext::builder("build")
+                .diagram_cache(cache.clone())
+                .output(output.clone());
+            docs(&config, files).render_with(builder).unwrap();
+            assert!(page(&output, "index.html")
+                .contains("<figure class=\"diagram diagram-dot\"><svg></svg></figure>"));
+        }
+        let runs = std::fs::read_to_string(&log).unwrap();
+        std::fs::remove_file(&log).unwrap();
+
+        assert_eq!(1, runs.matches("<svg>").count());
+    }
+
+    #[test]
+    fn render_reads_each_page_once() {
+        let reads = Rc::new(RefCell::new(HashMap::new()));
+        let config = "[languages]\nen = \"English\"\nja = \"日本語\"\n";
+        let fs = MemoryFs::with_config(config, TRANSLATED);
+        let docket = Docket::open_with("docs", CountingFs(fs, Rc::clone(&reads))).unwrap();
+        render(docket);
+
+        let reads = reads.borrow();
+        let pages: Vec<_> = reads
+            .iter()
+            .filter(|(path, _)| utils::normalised_path_ext(path).as_deref() == Some("md"))
+            .collect();
+        assert_eq!(5, pages.len());
+        assert!(pages.iter().all(|(_, &count)| count == 1), "{:?}", pages);
+    }
+
+    #[test]
+    fn strict_render_fails_on_warnings() {
+        let builder = RenderContext::builder("build")
+            .output(MemoryOutput::new())
+            .strict(true);
+        match example().render_with(builder) {
+            Err(Error::Strict(warnings)) => assert_eq!(1, warnings.len()),
+            other => panic!("Expected strict mode to fail, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn strict_render_fails_on_missing_files() {
+        let docs = docs(
+            "extra-css = [\"theme.css\"]\nstrings = \"missing.toml\"\n",
+            &[("docs/index.md", "# Home\n")],
+        );
+        let builder = RenderContext::builder("build")
+            .output(MemoryOutput::new())
+            .strict(true);
+        match docs.render_with(builder) {
+            Err(Error::Strict(warnings)) => assert_eq!(
+                vec![
+                    "docs/docket.toml: warning: extra asset `theme.css` does not exist",
+                    "docs/docket.toml: warning: strings file `missing.toml` does not exist",
+                ],
+                warnings.iter().map(ToString::to_string).collect::<Vec<_>>()
+            ),
+            other => panic!("Expected strict mode to fail, got {:?}", other),
+        }
+    }
+}
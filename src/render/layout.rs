@@ -4,18 +4,21 @@
 //! used to abstract over the exact HTML that is written.
 
 mod html;
+mod template;
 
 use super::{PageKind, RenderState};
-use crate::{asset::Asset, doctree, error::Result};
+use crate::{asset::Asset, config, doctree, error::Result, source::Source};
 use html::HtmlLayout;
+use log::info;
 use std::io::Write;
+use template::Templates;
 
 /// Layout Trait
 ///
 /// Layouts are responsible for writing out the contents of pages to files. A
 /// layout recieves a reference to the current render state, and information
 /// about the current page.
-pub(crate) trait Layout {
+pub trait Layout {
     /// Render a Page
     ///
     /// Layout rendering should write a representaiton of the `page` to the
@@ -38,6 +41,23 @@ pub(crate) trait Layout {
 
 // Get the dfault layout
 pub(crate) fn get_default_layout<'a>() -> &'a dyn Layout {
-    const DEFAULT: HtmlLayout = HtmlLayout;
+    static DEFAULT: HtmlLayout = HtmlLayout::new();
     &DEFAULT
 }
+
+/// Load the Layout for a Source
+///
+/// If the documentation source contains a templates directory then an HTML
+/// layout using those templates is returned. Otherwise `None` is returned and
+/// the default layout should be used.
+pub(crate) fn load_layout(source: &Source) -> Result<Option<Box<dyn Layout>>> {
+    let dir = source.root().join(config::TEMPLATES_DIR_NAME);
+    if !source.fs().is_dir(&dir) {
+        return Ok(None);
+    }
+    info!("Loading custom templates from {:?}", dir);
+    Ok(Some(Box::new(HtmlLayout::with_templates(Templates::load(
+        source.fs(),
+        &dir,
+    )?))))
+}
@@ -2,14 +2,18 @@ use crate::{
     asset::Asset,
     doctree::Page,
     error::Result,
-    highlight,
-    render::{CardSummary, NavInfo, PageKind, RenderState},
+    feed,
+    locale::Strings,
+    render::{CardSummary, NavInfo, PageKind, PageLink, RenderState, SwitcherLink},
+    sitemap,
+    source::Source,
     toc::{Nodes, Toc, TocElement, TocNode},
+    utils,
 };
 use pulldown_cmark::HeadingLevel;
-use std::{fmt, io::Write};
+use std::{collections::HashMap, fmt, io::Write, path::PathBuf};
 
-use super::Layout;
+use super::{template, template::Templates, Layout};
 
 struct Breadcrumbs<'a>(&'a RenderState<'a, 'a>, &'a str);
 
@@ -84,16 +88,17 @@ impl<'a> fmt::Display for InlineBreadcrumbs<'a> {
     }
 }
 
-struct Content<'a>(&'a Toc, &'a str);
+struct Content<'a>(&'a Toc, &'a str, &'a Strings);
 
 impl<'a> fmt::Display for Content<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let root = self.1;
+        let permalink = self.2.html("permalink");
         for element in self.0.walk_elements() {
             match element {
                 TocElement::Html(htm) => htm.fmt(f)?,
                 TocElement::TocReference => {
-                    write!(f, "<nav class='inline-toc'><p class='toc-section-label'>In this section</p>")?;
+                    write!(f, "<nav class='inline-toc'><p class='toc-section-label'>{}</p>", self.2.html("in-this-section"))?;
                     render_toc_to(f, self.0.nodes(), HeadingLevel::H2)?;
                     write!(f, "</nav>")?;
                 }
@@ -101,10 +106,11 @@ impl<'a> fmt::Display for Content<'a> {
                 // handled by the recurse from the walker.
                 TocElement::Node(nested) => write!(
                     f,
-                    "<{level} id='{slug}'>{heading}<a class='heading-anchor' href='#{slug}' aria-label='Permalink to this heading'><svg class='anchor-icon' viewBox='0 0 24 24' fill='none' stroke='currentColor' stroke-width='2' stroke-linecap='round' stroke-linejoin='round' aria-hidden='true'><use href='{root}feather-sprite.svg#copy'/></svg></a></{level}>",
+                    "<{level} id='{slug}'>{heading}<a class='heading-anchor' href='#{slug}' aria-label='{permalink}'><svg class='anchor-icon' viewBox='0 0 24 24' fill='none' stroke='currentColor' stroke-width='2' stroke-linecap='round' stroke-linejoin='round' aria-hidden='true'><use href='{root}feather-sprite.svg#copy'/></svg></a></{level}>",
                     level = &nested.heading.level,
                     slug = &nested.heading.slug,
                     heading = &nested.heading.contents,
+                    permalink = permalink,
                     root = root,
                 )?,
             }
@@ -149,14 +155,18 @@ impl<'a> fmt::Display for Cards<'a> {
 }
 
 /// Renders a compact link list of child pages below the sidebar TOC.
-struct CardLinks<'a>(&'a [CardSummary]);
+struct CardLinks<'a>(&'a [CardSummary], &'a Strings);
 
 impl<'a> fmt::Display for CardLinks<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.0.is_empty() {
             return Ok(());
         }
-        write!(f, "<nav class='child-links'><h2>Pages</h2><ul class='toc'>")?;
+        write!(
+            f,
+            "<nav class='child-links'><h2>{}</h2><ul class='toc'>",
+            self.1.html("pages")
+        )?;
         for card in self.0 {
             write!(
                 f,
@@ -178,7 +188,11 @@ impl<'a> fmt::Display for CardLinks<'a> {
 /// rendered directly so the page title is not repeated in the TOC.
 fn render_toc_to(f: &mut fmt::Formatter<'_>, nodes: Nodes, limit: HeadingLevel) -> fmt::Result {
     if nodes.full_width() == 1 {
-        let inner_nodes = nodes.into_iter().next().unwrap().nodes();
+        // A page without headings has a single element which isn't a node.
+        let inner_nodes = match nodes.into_iter().next() {
+            Some(node) => node.nodes(),
+            None => return Ok(()),
+        };
         if inner_nodes.full_width() > 0 {
             return render_toc_node_to(f, inner_nodes, limit);
         }
@@ -213,50 +227,60 @@ fn render_toc_node_to<'a>(
     write!(f, "</ul>")
 }
 
-/// Renderable struct
+/// Renders the navigation tree for the whole site. Holds the tree, the path
+/// to the site root, and the current page's path from the root.
 struct Navs<'a>(&'a [NavInfo], &'a str, &'a str);
 
 impl<'a> fmt::Display for Navs<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        render_nav_list(f, self.0, self.1, self.2)
+        render_nav_list(f, self.0, "", self.1, self.2)
     }
 }
 
 /// Recursively render a navigation list.
 ///
 /// Items without children render as plain links; items with children render
-/// as `<details>/<summary>` disclosure widgets with a nested list.
+/// as `<details>/<summary>` disclosure widgets with a nested list. Bales which
+/// contain the `current` page are open, and the link to the page itself is
+/// marked as current. Each item's path from the root is its `prefix` followed
+/// by its slug.
 fn render_nav_list(
     f: &mut fmt::Formatter<'_>,
     navs: &[NavInfo],
     prefix: &str,
     root: &str,
+    current: &str,
 ) -> fmt::Result {
     if navs.is_empty() {
         return Ok(());
     }
     write!(f, "<ul class='site-nav-list'>")?;
     for nav in navs {
+        let path = format!("{}{}/", prefix, nav.slug);
+        let aria_current = if path == current {
+            " aria-current='page'"
+        } else {
+            ""
+        };
         write!(f, "<li>")?;
         if nav.children.is_empty() {
             write!(
                 f,
-                "<a href='{prefix}{slug}'>{title}</a>",
-                prefix = prefix,
-                slug = nav.slug,
+                "<a href='{root}{path}'{aria_current}>{title}</a>",
                 title = nav.title,
             )?;
         } else {
-            let child_prefix = format!("{}{}/", prefix, nav.slug);
+            let open = if current.starts_with(&path) {
+                " open"
+            } else {
+                ""
+            };
             write!(
                 f,
-                "<details><summary><svg class='nav-chevron' viewBox='0 0 24 24' fill='none' stroke='currentColor' stroke-width='2' stroke-linecap='round' stroke-linejoin='round' aria-hidden='true'><use href='{root}feather-sprite.svg#chevron-right'/></svg><a href='{prefix}{slug}'>{title}</a></summary>",
-                root = root,
-                prefix = prefix,
-                slug = nav.slug,
+                "<details{open}><summary><svg class='nav-chevron' viewBox='0 0 24 24' fill='none' stroke='currentColor' stroke-width='2' stroke-linecap='round' stroke-linejoin='round' aria-hidden='true'><use href='{root}feather-sprite.svg#chevron-right'/></svg><a href='{root}{path}'{aria_current}>{title}</a></summary>",
                 title = nav.title,
             )?;
-            render_nav_list(f, &nav.children, &child_prefix, root)?;
+            render_nav_list(f, &nav.children, &path, root, current)?;
             write!(f, "</details>")?;
         }
         write!(f, "</li>")?;
@@ -264,56 +288,303 @@ fn render_nav_list(
     write!(f, "</ul>")
 }
 
-fn get_footer<'a>(state: &'a RenderState) -> &'a str {
-    state
-        .current_bale()
-        .footer()
-        .unwrap_or("<p>Rendered by <a href='https://github.com/iwillspeak/docket/'>Docket</a></p>")
+fn get_footer(state: &RenderState, strings: &Strings) -> String {
+    match state.current_bale().footer() {
+        Some(footer) => footer.to_owned(),
+        None => format!(
+            "<p>{}</p>",
+            strings.html("rendered-by").replace(
+                "{docket}",
+                "<a href='https://github.com/iwillspeak/docket/'>Docket</a>"
+            )
+        ),
+    }
 }
 
-/// Convert a `SystemTime` to a `YYYY-MM-DD` date string.
-///
-/// Uses Howard Hinnant's `civil_from_days` algorithm to avoid any external
-/// date/time dependency.
-fn format_date(t: std::time::SystemTime) -> String {
-    use std::time::UNIX_EPOCH;
-    let secs = match t.duration_since(UNIX_EPOCH) {
-        Ok(d) => d.as_secs() as i64,
-        Err(_) => return String::from("unknown"),
-    };
-    let z = secs / 86400 + 719468;
-    let era = if z >= 0 { z } else { z - 146096 } / 146097;
-    let doe = (z - era * 146097) as u64;
-    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
-    let y = yoe as i64 + era * 400;
-    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
-    let mp = (5 * doy + 2) / 153;
-    let d = doy - (153 * mp + 2) / 5 + 1;
-    let m = if mp < 10 { mp + 3 } else { mp - 9 };
-    let y = if m <= 2 { y + 1 } else { y };
-    format!("{:04}-{:02}-{:02}", y, m, d)
-}
+/// Renders the last-updated date and contributors, or nothing.
+struct LastUpdated<'a>(Option<std::time::SystemTime>, &'a [String], &'a Strings);
 
-/// Renders the last-updated mini-footer inside the article, or nothing.
-struct LastUpdated(Option<std::time::SystemTime>);
+impl<'a> LastUpdated<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_none() && self.1.is_empty()
+    }
+}
 
-impl fmt::Display for LastUpdated {
+impl<'a> fmt::Display for LastUpdated<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+        write!(f, "<p class='page-updated'>")?;
         if let Some(t) = self.0 {
+            let date = match self.2.format_date(t) {
+                Some(date) => utils::escape_html(date),
+                None => self.2.html("unknown-date"),
+            };
+            write!(f, "{} {}", self.2.html("last-updated"), date)?;
+        }
+        if !self.1.is_empty() {
+            if self.0.is_some() {
+                write!(f, " &middot; ")?;
+            }
+            write!(f, "{} ", self.2.html("contributors"))?;
+            for (idx, author) in self.1.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", utils::escape_html(author))?;
+            }
+        }
+        write!(f, "</p>")
+    }
+}
+
+/// Renders a link to the page's source file, or nothing.
+struct EditLink<'a>(Option<String>, &'a Strings);
+
+impl<'a> EditLink<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+impl<'a> fmt::Display for EditLink<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(url) = &self.0 {
+            write!(
+                f,
+                "<p class='page-edit'><a href='{}'>{}</a></p>",
+                utils::escape_html(url),
+                self.1.html("edit-this-page")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the page metadata mini-footer inside the article, or nothing.
+struct PageMeta<'a>(LastUpdated<'a>, EditLink<'a>);
+
+impl<'a> fmt::Display for PageMeta<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() && self.1.is_empty() {
+            return Ok(());
+        }
+        write!(f, "<div class='page-meta'>{}{}</div>", self.0, self.1)
+    }
+}
+
+/// Renders links to the previous and next pages in the reading order.
+struct PageNav<'a>(
+    Option<&'a PageLink>,
+    Option<&'a PageLink>,
+    &'a str,
+    &'a Strings,
+);
+
+impl<'a> fmt::Display for PageNav<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_none() && self.1.is_none() {
+            return Ok(());
+        }
+        let root = self.2;
+        write!(
+            f,
+            "<nav class='page-nav' aria-label='{}'>",
+            self.3.html("pages")
+        )?;
+        for (link, class, rel, label) in [
+            (self.0, "page-nav-prev", "prev", self.3.html("previous")),
+            (self.1, "page-nav-next", "next", self.3.html("next")),
+        ] {
+            if let Some(link) = link {
+                let href = format!("{}{}", root, link.path);
+                write!(
+                    f,
+                    "<a class='{class}' href='{href}' rel='{rel}'>\
+                        <span class='page-nav-label'>{label}</span>\
+                        <span class='page-nav-title'>{title}</span>\
+                    </a>",
+                    class = class,
+                    href = if href.is_empty() { "./" } else { &href },
+                    rel = rel,
+                    label = label,
+                    title = link.title,
+                )?;
+            }
+        }
+        write!(f, "</nav>")
+    }
+}
+
+/// Renders the description, canonical link, and social metadata for a page.
+struct PageMetadata {
+    /// The page title, as plain text
+    title: String,
+    /// The documentation title, as plain text
+    site_name: String,
+    /// A short description of the page
+    description: Option<String>,
+    /// The absolute URL of the page, if the site's base URL is known
+    url: Option<String>,
+    /// Is this the index page of a bale
+    index: bool,
+}
+
+impl fmt::Display for PageMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = self.description.as_deref().map(utils::escape_html);
+        if let Some(description) = &description {
+            write!(f, "<meta name=\"description\" content=\"{}\">", description)?;
+        }
+        let url = self.url.as_deref().map(utils::escape_html);
+        if let Some(url) = &url {
+            write!(f, "\n    <link rel=\"canonical\" href=\"{}\">", url)?;
+        }
+
+        let title = utils::escape_html(&self.title);
+        let og_type = if self.index { "website" } else { "article" };
+        write!(
+            f,
+            "\n    <meta property=\"og:type\" content=\"{}\">\
+             \n    <meta property=\"og:site_name\" content=\"{}\">\
+             \n    <meta property=\"og:title\" content=\"{}\">",
+            og_type,
+            utils::escape_html(&self.site_name),
+            title
+        )?;
+        if let Some(description) = &description {
+            write!(
+                f,
+                "\n    <meta property=\"og:description\" content=\"{}\">",
+                description
+            )?;
+        }
+        if let Some(url) = &url {
+            write!(f, "\n    <meta property=\"og:url\" content=\"{}\">", url)?;
+        }
+        write!(
+            f,
+            "\n    <meta name=\"twitter:card\" content=\"summary\">\
+             \n    <meta name=\"twitter:title\" content=\"{}\">",
+            title
+        )?;
+        if let Some(description) = &description {
             write!(
                 f,
-                "<p class='page-updated'>Last updated: {}</p>",
-                format_date(t)
+                "\n    <meta name=\"twitter:description\" content=\"{}\">",
+                description
             )?;
         }
         Ok(())
     }
 }
 
+/// Renders a link to the current bale's feed, if it has one.
+struct FeedLink<'a>(Option<&'a str>, &'a str);
+
+impl<'a> fmt::Display for FeedLink<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(title) = self.0 {
+            write!(
+                f,
+                "<link rel=\"alternate\" type=\"application/atom+xml\" title=\"{}\" href=\"{}{}\">",
+                utils::escape_html(utils::html_to_text(title)),
+                self.1,
+                feed::FEED_FILE_NAME
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders a menu linking to the current page in each version or language of
+/// the site, with the given class.
+struct Switcher<'a>(&'a [SwitcherLink], &'a str);
+
+impl<'a> fmt::Display for Switcher<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(current) = self.0.iter().find(|link| link.current) else {
+            return Ok(());
+        };
+        write!(
+            f,
+            "<details class='{}'><summary>{}</summary><ul>",
+            self.1,
+            utils::escape_html(&current.name)
+        )?;
+        for link in self.0 {
+            let class = if link.current { " class='current'" } else { "" };
+            write!(
+                f,
+                "<li{}><a href='{}'>{}</a></li>",
+                class,
+                utils::escape_html(&link.href),
+                utils::escape_html(&link.name)
+            )?;
+        }
+        write!(f, "</ul></details>")
+    }
+}
+
+/// Renders links to the web fonts used by the default style, unless offline.
+struct WebFonts(bool);
+
+impl fmt::Display for WebFonts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 {
+            return Ok(());
+        }
+        write!(
+            f,
+            r#"<link rel="preconnect" href="https://fonts.googleapis.com">
+    <link rel="preconnect" href="https://fonts.gstatic.com" crossorigin>
+    <link href="https://fonts.googleapis.com/css2?family=Montserrat&family=JetBrains+Mono&display=swap" rel="stylesheet">"#
+        )
+    }
+}
+
+/// Renders links to the extra stylesheets and scripts from the source.
+struct ExtraAssets<'a>(&'a Source, &'a str);
+
+impl<'a> fmt::Display for ExtraAssets<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hrefs = |paths: &'a [PathBuf]| {
+            paths
+                .iter()
+                .map(|path| utils::url_encode_path(self.0.extra_site_path(path)))
+        };
+        for href in hrefs(self.0.extra_css()) {
+            write!(f, "<link rel=\"stylesheet\" href=\"{}{}\">", self.1, href)?;
+        }
+        for href in hrefs(self.0.extra_js()) {
+            write!(f, "<script src=\"{}{}\" defer></script>", self.1, href)?;
+        }
+        Ok(())
+    }
+}
+
 /// The HTML Layout
 ///
-/// This struct implements the `Layout` trait to allow rendering pages.
-pub(super) struct HtmlLayout;
+/// This struct implements the `Layout` trait to allow rendering pages. The
+/// page shell is expanded from a set of templates, which can be overridden.
+pub(super) struct HtmlLayout {
+    templates: Templates,
+}
+
+impl HtmlLayout {
+    /// Create a Layout with the Default Templates
+    pub const fn new() -> Self {
+        HtmlLayout {
+            templates: Templates::new(),
+        }
+    }
+
+    /// Create a Layout using the given `templates`
+    pub fn with_templates(templates: Templates) -> Self {
+        HtmlLayout { templates }
+    }
+}
 
 impl Layout for HtmlLayout {
     fn render(
@@ -323,101 +594,124 @@ impl Layout for HtmlLayout {
         kind: PageKind,
         page: &Page,
     ) -> Result<()> {
-        let nav_prefix = kind.path_to_bale();
+        let nav_prefix = state.path_to_bale(&kind);
         let root = state.path_to_root(&kind);
         let cards = match &kind {
             PageKind::Index(summaries) => summaries.as_slice(),
-            PageKind::Nested(_) => &[],
+            PageKind::Nested(_) | PageKind::NotFound => &[],
+        };
+        let (prev, next) = state.neighbours(&kind);
+        let source = &state.ctx().source;
+        let strings = state.ctx().strings();
+        let edit_url = page
+            .source_path()
+            .and_then(|path| state.ctx().config().edit_url_for(path));
+        let metadata = PageMetadata {
+            title: utils::html_to_text(page.title()),
+            site_name: utils::html_to_text(&state.ctx().site_name),
+            description: page.description(),
+            // The not found page doesn't have a URL of its own.
+            url: state
+                .ctx()
+                .base_url()
+                .filter(|_| !matches!(kind, PageKind::NotFound))
+                .map(|base_url| sitemap::absolute_url(&base_url, &state.path_from_root(&kind))),
+            index: matches!(kind, PageKind::Index(_)),
         };
+        // Feeds are only written when the base URL is known.
+        let feed_title = Some(state.current_bale())
+            .filter(|bale| bale.is_feed() && source.config().base_url.is_some())
+            .map(|bale| bale.title());
         let hl_header = {
             let mut buf = Vec::new();
-            highlight::get_hilighter().write_header(&mut buf, &root)?;
+            state.ctx().highlighter().write_header(&mut buf, &root)?;
             match String::from_utf8(buf) {
                 Ok(s) => s,
                 Err(e) => String::from_utf8_lossy(&e.into_bytes()).into_owned(),
             }
         };
-        write!(
-            writer,
-            r##"<!DOCTYPE html>
-<html>
-<head>
-    <title>{site_name} | {page_title}</title>
-    <meta name="viewport" content="width=device-width,initial-scale=1">
-    <meta charset="UTF-8">
-    <link rel="preconnect" href="https://fonts.googleapis.com">
-    <link rel="preconnect" href="https://fonts.gstatic.com" crossorigin>
-    <link href="https://fonts.googleapis.com/css2?family=Montserrat&family=JetBrains+Mono&display=swap" rel="stylesheet">
-    <link rel="stylesheet" href="{root}style.css">
-    {hl_header}
-    <script src="{root}dark.js" type=module></script>
-    <script src="{root}search.js" type=module></script>
-    <script src="{root}nav.js" type=module></script>
-    <script src="{root}permalink.js" type=module></script>
-</head>
-<body>
-    <header class="site-head">
-        <div class="content">
-            <button class="nav-toggle header-icon-btn" id="nav-toggle" aria-label="Toggle navigation" aria-expanded="false" aria-controls="sidebar">
-                <svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round" aria-hidden="true"><use href='{root}feather-sprite.svg#menu'/></svg>
-            </button>
-            <nav class="breadcrumbs">{breadcrumbs}</nav>
-            <div id="dark-mode-placeholder"></div>
-            <button class="toc-toggle header-icon-btn" id="toc-toggle" aria-label="Toggle table of contents" aria-expanded="false" aria-controls="toc-panel">
-                <svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round" aria-hidden="true"><use href='{root}feather-sprite.svg#more-vertical'/></svg>
-            </button>
-        </div>
-    </header>
-    <section class="content doc-grid">
-        <aside class="sidebar" id="sidebar">
-            <button class="drawer-close" aria-label="Close navigation">
-                <svg width="18" height="18" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round" aria-hidden="true"><use href='{root}feather-sprite.svg#x'/></svg>
-            </button>
-            <div id="docket-search"></div>
-            <nav class="site-nav">
-                <h2>In this section</h2>
-                {navs}
-            </nav>
-        </aside>
-        <nav class="toc-tree" id="toc-panel">
-            <button class="drawer-close" aria-label="Close table of contents">
-                <svg width="18" height="18" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round" aria-hidden="true"><use href='{root}feather-sprite.svg#x'/></svg>
-            </button>
-            <h2>On this Page</h2>
-            {toc}
-            {card_links}
-        </nav>
-        <main>
-            <nav class="breadcrumbs breadcrumbs-inline">{inline_breadcrumbs}</nav>
-            <article id="document-content">
-                {content}
-                {child_cards}
-                {last_updated}
-            </article>
-        </main>
-    </section>
-    <footer><div class="content">{footer}</div></footer>
-</body>
-</html>"##,
-            site_name = state.ctx().site_name,
-            root = root,
-            hl_header = hl_header,
-            breadcrumbs = Breadcrumbs(state, nav_prefix),
-            inline_breadcrumbs = InlineBreadcrumbs(state, nav_prefix, &root),
-            page_title = page.title(),
-            navs = Navs(&state.navs, nav_prefix, &root),
-            toc = RenderedToc(page.content(), HeadingLevel::H4),
-            card_links = CardLinks(cards),
-            content = Content(page.content(), &root),
-            child_cards = Cards(cards),
-            last_updated = LastUpdated(page.modified()),
-            footer = get_footer(state)
-        )?;
+        let values: HashMap<&str, String> = [
+            ("site_name", state.ctx().site_name.clone()),
+            ("page_title", page.title().to_owned()),
+            ("root", root.clone()),
+            ("lang", utils::escape_html(&source.config().lang)),
+            ("meta", metadata.to_string()),
+            ("feed", FeedLink(feed_title, &nav_prefix).to_string()),
+            ("hl_header", hl_header),
+            ("web_fonts", WebFonts(source.config().offline).to_string()),
+            ("extras", ExtraAssets(source, &root).to_string()),
+            ("breadcrumbs", Breadcrumbs(state, &nav_prefix).to_string()),
+            (
+                "versions",
+                Switcher(&state.version_links(&kind), "version-switcher").to_string(),
+            ),
+            (
+                "languages",
+                Switcher(&state.language_links(&kind), "language-switcher").to_string(),
+            ),
+            (
+                "inline_breadcrumbs",
+                InlineBreadcrumbs(state, &nav_prefix, &root).to_string(),
+            ),
+            (
+                "navs",
+                Navs(&state.ctx().navigation, &root, &state.path_from_root(&kind)).to_string(),
+            ),
+            (
+                "toc",
+                RenderedToc(page.content(), HeadingLevel::H4).to_string(),
+            ),
+            ("card_links", CardLinks(cards, &strings).to_string()),
+            (
+                "content",
+                Content(page.content(), &root, &strings).to_string(),
+            ),
+            ("child_cards", Cards(cards).to_string()),
+            (
+                "page_meta",
+                PageMeta(
+                    LastUpdated(page.modified(), page.authors(), &strings),
+                    EditLink(edit_url, &strings),
+                )
+                .to_string(),
+            ),
+            ("page_nav", PageNav(prev, next, &root, &strings).to_string()),
+            ("footer_content", get_footer(state, &strings)),
+        ]
+        .into_iter()
+        .collect();
+        // Interface strings can be used in the templates as `strings.<name>`.
+        let texts: HashMap<String, String> = strings
+            .keys()
+            .map(|key| (format!("strings.{}", key), strings.html(key)))
+            .collect();
+
+        // Expand each of the named blocks, and then the page itself.
+        let blocks: HashMap<&str, String> = template::BLOCKS
+            .iter()
+            .map(|(name, _)| {
+                let block = template::expand(self.templates.get(name), |name| {
+                    values
+                        .get(name)
+                        .or_else(|| texts.get(name))
+                        .map(String::as_str)
+                });
+                (*name, block)
+            })
+            .collect();
+        let html = template::expand(self.templates.get(template::PAGE), |name| {
+            blocks
+                .get(name)
+                .or_else(|| values.get(name))
+                .or_else(|| texts.get(name))
+                .map(String::as_str)
+        });
+        writer.write_all(html.as_bytes())?;
         Ok(())
     }
 
     fn assets(&self) -> Result<Vec<Asset>> {
-        let mut assets = vec![
+        Ok(vec![
             Asset::internal("style.css", include_str!("../../../assets/style.css")),
             Asset::internal("search.js", include_str!("../../../assets/search.js")),
             Asset::internal("dark.js", include_str!("../../../assets/dark.js")),
@@ -430,8 +724,43 @@ impl Layout for HtmlLayout {
                 "feather-sprite.svg",
                 include_str!("../../../assets/feather-sprite.svg"),
             ),
-        ];
-        assets.extend(highlight::get_hilighter().assets()?);
-        Ok(assets)
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::Config, diagram::DiagramCache, highlight, locale::Locales};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn render_toc(markdown: &str) -> String {
+        let config = Config::default();
+        let toc = Toc::new(
+            markdown,
+            &config,
+            highlight::get_hilighter(&config),
+            &DiagramCache::default(),
+        );
+        RenderedToc(&toc, HeadingLevel::H4).to_string()
+    }
+
+    #[test]
+    fn render_toc_without_headings() {
+        assert_eq!("", render_toc("Just a paragraph.\n"));
+        assert_eq!(
+            "<ul class='toc'><li><a href='#setup'>setup</a><ul class='toc'></ul></ul>",
+            render_toc("# Title\n\n## setup\n")
+        );
+    }
+
+    #[test]
+    fn render_unknown_last_updated_date() {
+        let strings = Locales::built_in().strings("de");
+        let before_epoch = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(
+            "<p class='page-updated'>Zuletzt aktualisiert: unbekannt</p>",
+            LastUpdated(Some(before_epoch), &[], &strings).to_string()
+        );
     }
 }
@@ -0,0 +1,170 @@
+//! HTML Templates
+//!
+//! The page shell for the HTML layout is built from a small set of named
+//! templates. Each template is plain HTML with `{{ name }}` placeholders which
+//! are replaced with pre-rendered values such as the page's content, TOC, or
+//! navigation. Templates don't support any logic; anything more complex than
+//! substitution is handled by the layout when it renders the values.
+//!
+//! Docket ships a default for each template. A `_templates/` directory in the
+//! root of the documentation can override any of them by providing a file
+//! named after the template, e.g. `_templates/footer.html`.
+
+use std::{collections::BTreeMap, path::Path};
+
+use log::{info, warn};
+
+use crate::{error::Result, source::SourceFs};
+
+/// The template for the whole page. Placeholders for each of the other named
+/// blocks mark where they are inserted.
+pub(super) const PAGE: &str = "page";
+
+/// Named blocks, in the order they are expanded, along with their defaults.
+pub(super) const BLOCKS: &[(&str, &str)] = &[
+    ("head", include_str!("../../../assets/templates/head.html")),
+    (
+        "header",
+        include_str!("../../../assets/templates/header.html"),
+    ),
+    (
+        "sidebar",
+        include_str!("../../../assets/templates/sidebar.html"),
+    ),
+    (
+        "footer",
+        include_str!("../../../assets/templates/footer.html"),
+    ),
+];
+
+/// The default whole-page template.
+const DEFAULT_PAGE: &str = include_str!("../../../assets/templates/page.html");
+
+/// Template Set
+///
+/// Holds any user-provided templates. Templates which haven't been overridden
+/// fall back to the built-in defaults.
+#[derive(Debug, Default)]
+pub(super) struct Templates(BTreeMap<String, String>);
+
+impl Templates {
+    /// Create an Empty Template Set
+    ///
+    /// With no overrides every template uses the built-in default.
+    pub const fn new() -> Self {
+        Templates(BTreeMap::new())
+    }
+
+    /// Load Templates from a Directory
+    ///
+    /// Reads an override for each known template from `<name>.html` within the
+    /// given directory. Other files in the directory are ignored.
+    pub fn load(fs: &dyn SourceFs, dir: &Path) -> Result<Self> {
+        let mut overrides = BTreeMap::new();
+        for path in fs.read_dir(dir)? {
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) if path.extension().is_some_and(|ext| ext == "html") => name,
+                _ => {
+                    warn!("Ignoring unexpected file in templates: {:?}", path);
+                    continue;
+                }
+            };
+            if name != PAGE && !BLOCKS.iter().any(|(block, _)| *block == name) {
+                warn!("Ignoring unknown template {:?}", path);
+                continue;
+            }
+            info!("Using custom template {:?}", path);
+            overrides.insert(name.to_owned(), fs.read_to_string(&path)?);
+        }
+        Ok(Templates(overrides))
+    }
+
+    /// Get the source for the named template
+    pub fn get(&self, name: &str) -> &str {
+        let source = match self.0.get(name) {
+            Some(source) => source.as_str(),
+            None if name == PAGE => DEFAULT_PAGE,
+            None => BLOCKS
+                .iter()
+                .find(|(block, _)| *block == name)
+                .map(|(_, source)| *source)
+                .unwrap_or_default(),
+        };
+        // Blocks are inserted on their own line in the page template, so
+        // drop the trailing newline most editors will have added.
+        source.strip_suffix('\n').unwrap_or(source)
+    }
+}
+
+/// Expand a Template
+///
+/// Replaces each `{{ name }}` placeholder in the `template` with the value
+/// returned by `lookup`. Placeholders with no value are left as they are, so
+/// that mistakes in custom templates are visible in the output.
+pub(super) fn expand<'a, F>(template: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<&'a str>,
+{
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let placeholder = &rest[start..start + len + 2];
+        output.push_str(&rest[..start]);
+        match lookup(placeholder[2..len].trim()) {
+            Some(value) => output.push_str(value),
+            None => output.push_str(placeholder),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<&'static str> {
+        match name {
+            "title" => Some("Hello"),
+            "empty" => Some(""),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expand_without_placeholders() {
+        assert_eq!("", expand("", lookup));
+        assert_eq!("<p>plain</p>", expand("<p>plain</p>", lookup));
+    }
+
+    #[test]
+    fn expand_replaces_placeholders() {
+        assert_eq!("<h1>Hello</h1>", expand("<h1>{{ title }}</h1>", lookup));
+        assert_eq!("HelloHello", expand("{{title}}{{  title  }}", lookup));
+        assert_eq!("[]", expand("[{{ empty }}]", lookup));
+    }
+
+    #[test]
+    fn expand_leaves_unknown_placeholders() {
+        assert_eq!(
+            "{{ missing }} Hello",
+            expand("{{ missing }} {{ title }}", lookup)
+        );
+        assert_eq!(
+            "Hello {{ unclosed",
+            expand("{{ title }} {{ unclosed", lookup)
+        );
+    }
+
+    #[test]
+    fn default_templates_have_no_trailing_newline() {
+        let templates = Templates::new();
+        assert!(templates.get(PAGE).starts_with("<!DOCTYPE html>"));
+        assert!(templates.get("footer").ends_with("</footer>"));
+        assert_eq!("", templates.get("unknown"));
+    }
+}
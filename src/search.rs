@@ -6,17 +6,27 @@
 //!
 //! Terms are normalised before indexing: stopwords are removed, very short
 //! words are dropped, and the remaining words are reduced to their stem using
-//! the Snowball English (Porter 2) algorithm. The final JSON uses TF-IDF
-//! weights so that words common across many pages are down-weighted relative
-//! to words that are distinctive to a few pages.
+//! the Snowball algorithm for the documentation's language. The final JSON
+//! uses TF-IDF weights so that words common across many pages are
+//! down-weighted relative to words that are distinctive to a few pages.
+//!
+//! Chinese and Japanese text isn't written with spaces between words, so runs
+//! of Han, Hiragana, and Katakana characters are instead indexed as
+//! overlapping pairs of characters. A query for a word within a sentence then
+//! matches each of the pairs that make it up.
+//!
+//! English search queries are stemmed in the browser. For other languages the
+//! stem of each indexed word is written to `search_stems.json` so queries can
+//! be stemmed by looking their words up.
 
 use rust_stemmers::{Algorithm, Stemmer};
 use serde::Serialize;
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap};
 use std::io;
 use std::path::Path;
 
+use crate::output::OutputSink;
+
 /// Common English stopwords to exclude from the search index.
 ///
 /// This list must remain sorted so that `binary_search` works correctly.
@@ -30,11 +40,58 @@ static STOPWORDS: &[&str] = &[
     "we", "were", "which", "who", "will", "with", "would", "you", "your",
 ];
 
+/// The name of the file listing the stem of each word, for languages which
+/// can't be stemmed in the browser
+const STEMS_FILE_NAME: &str = "search_stems.json";
+
 /// Returns `true` when `word` (already lower-cased) is a stopword.
 fn is_stopword(word: &str) -> bool {
     STOPWORDS.binary_search(&word).is_ok()
 }
 
+/// Returns `true` for Han, Hiragana, and Katakana characters.
+///
+/// These ranges must match the `cjk` pattern in `search.js`.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3005}'..='\u{3007}'
+        | '\u{3040}'..='\u{30ff}'
+        | '\u{31f0}'..='\u{31ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{ff66}'..='\u{ff9f}'
+        | '\u{20000}'..='\u{2ffff}')
+}
+
+/// Split a `word` into runs of CJK characters and runs of other characters.
+///
+/// Each run is paired with `true` if it is CJK text.
+fn split_cjk(word: &str) -> Vec<(bool, &str)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut chars = word.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let cjk = is_cjk(c);
+        let end = chars.peek().map_or(word.len(), |&(idx, _)| idx);
+        if chars.peek().is_none_or(|&(_, next)| is_cjk(next) != cjk) {
+            runs.push((cjk, &word[start..end]));
+            start = end;
+        }
+    }
+    runs
+}
+
+/// The overlapping pairs of characters in `run`, or the character itself if
+/// it is on its own.
+fn bigrams(run: &str) -> Vec<String> {
+    let chars: Vec<char> = run.chars().collect();
+    if chars.len() < 2 {
+        return vec![run.to_owned()];
+    }
+    chars.windows(2).map(|pair| pair.iter().collect()).collect()
+}
+
 /// Builder struct for search indices
 ///
 /// Keeps track of term counts as they are added. Once all terms have
@@ -42,55 +99,125 @@ fn is_stopword(word: &str) -> bool {
 pub(crate) struct TermFrequenciesBuilder {
     term_count: u32,
     terms: HashMap<String, u32>,
-    stemmer: Stemmer,
+    stemmer: Option<Stemmer>,
+    /// Is the text in English. Only English has stopwords, and is stemmed in
+    /// the browser.
+    english: bool,
+    /// The stem of each word which differs from it, for other languages
+    words: HashMap<String, String>,
 }
 
 impl Default for TermFrequenciesBuilder {
     fn default() -> Self {
+        Self::new("en")
+    }
+}
+
+impl TermFrequenciesBuilder {
+    /// Create a Builder for Text in a Language
+    ///
+    /// The `lang` is a BCP 47 tag, such as `en` or `pt-BR`. Languages without
+    /// a stemmer have their words indexed as they are.
+    pub fn new(lang: &str) -> Self {
+        let algorithm = stemmer_for(lang);
         Self {
             term_count: 0,
             terms: HashMap::new(),
-            stemmer: Stemmer::create(Algorithm::English),
+            stemmer: algorithm.map(Stemmer::create),
+            english: algorithm == Some(Algorithm::English),
+            words: HashMap::new(),
         }
     }
-}
 
-impl TermFrequenciesBuilder {
     pub fn add_terms(&mut self, text: &str) -> &mut Self {
-        for term in text.split(|c| {
-            c == '>' || c == '<' || char::is_whitespace(c) || char::is_ascii_punctuation(&c)
-        }) {
-            let term = term.trim().to_lowercase();
-            // Skip empty tokens, very short words, and stopwords.
-            if term.len() < 3 || is_stopword(&term) {
-                continue;
+        for word in text.split(|c: char| !c.is_alphanumeric()) {
+            for (cjk, run) in split_cjk(word) {
+                if cjk {
+                    for bigram in bigrams(run) {
+                        self.term_count += 1;
+                        *self.terms.entry(bigram).or_default() += 1;
+                    }
+                } else {
+                    self.add_word(run);
+                }
             }
-            let stemmed = self.stemmer.stem(&term).into_owned();
-            self.term_count += 1;
-            *self.terms.entry(stemmed).or_default() += 1;
         }
         self
     }
 
+    /// Add a single word, that isn't CJK text, to the index
+    fn add_word(&mut self, word: &str) {
+        let term = word.to_lowercase();
+        // Skip empty tokens, very short words, and stopwords.
+        if term.chars().count() < 3 || (self.english && is_stopword(&term)) {
+            return;
+        }
+        let stemmed = match &self.stemmer {
+            Some(stemmer) => stemmer.stem(&term).into_owned(),
+            None => term.clone(),
+        };
+        if !self.english && stemmed != term {
+            self.words.insert(term, stemmed.clone());
+        }
+        self.term_count += 1;
+        *self.terms.entry(stemmed).or_default() += 1;
+    }
+
     /// Finalise the Search Index
     ///
     /// Convert the term counts into a term frequencies index.
     pub fn finalise(self) -> TermFrequenciesIndex {
         let total: f64 = self.term_count.into();
         let terms = self.terms;
-        TermFrequenciesIndex(if total > 0.0 {
-            terms
-                .into_iter()
-                .map(|(term, count)| (term, f64::from(count) / total))
-                .collect()
-        } else {
-            HashMap::new()
-        })
+        TermFrequenciesIndex(
+            if total > 0.0 {
+                terms
+                    .into_iter()
+                    .map(|(term, count)| (term, f64::from(count) / total))
+                    .collect()
+            } else {
+                HashMap::new()
+            },
+            self.words,
+        )
     }
 }
 
+/// Get the Stemmer for a Language
+///
+/// Picks the Snowball algorithm from the primary subtag of the BCP 47 `lang`
+/// tag, if there is one for the language.
+fn stemmer_for(lang: &str) -> Option<Algorithm> {
+    let primary = lang.split(['-', '_']).next().unwrap_or_default();
+    Some(match &primary.to_ascii_lowercase()[..] {
+        "ar" => Algorithm::Arabic,
+        "da" => Algorithm::Danish,
+        "nl" => Algorithm::Dutch,
+        "en" => Algorithm::English,
+        "fi" => Algorithm::Finnish,
+        "fr" => Algorithm::French,
+        "de" => Algorithm::German,
+        "el" => Algorithm::Greek,
+        "hu" => Algorithm::Hungarian,
+        "it" => Algorithm::Italian,
+        "no" | "nb" | "nn" => Algorithm::Norwegian,
+        "pt" => Algorithm::Portuguese,
+        "ro" => Algorithm::Romanian,
+        "ru" => Algorithm::Russian,
+        "es" => Algorithm::Spanish,
+        "sv" => Algorithm::Swedish,
+        "ta" => Algorithm::Tamil,
+        "tr" => Algorithm::Turkish,
+        _ => return None,
+    })
+}
+
+/// Term Frequencies Index
+///
+/// The frequency of each stemmed term in a page, along with the stem of each
+/// word which can't be stemmed in the browser.
 #[derive(Debug)]
-pub struct TermFrequenciesIndex(HashMap<String, f64>);
+pub struct TermFrequenciesIndex(HashMap<String, f64>, HashMap<String, String>);
 
 impl TermFrequenciesIndex {
     /// Unpack the inner frequencies map from this index type
@@ -114,6 +241,12 @@ impl TermFrequenciesIndex {
     pub fn iter_frequencies(&self) -> impl Iterator<Item = (&String, &f64)> {
         self.0.iter()
     }
+
+    /// Iterate over the indexed words and their stems, for languages which
+    /// aren't stemmed in the browser
+    pub fn iter_words(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.1.iter()
+    }
 }
 
 /// Searchable Document Trait
@@ -149,7 +282,10 @@ struct SearchIndexEntry<'a> {
 /// TF scores from each page's index are combined with corpus-wide IDF values
 /// to produce TF-IDF weights. Terms that appear in every indexed page receive
 /// an IDF of zero and are omitted from the output to keep the index compact.
-pub(crate) fn write_search_indices<'a, I, D>(output_dir: &Path, pages: I) -> Result<(), io::Error>
+pub(crate) fn write_search_indices<'a, I, D>(
+    output: &dyn OutputSink,
+    pages: I,
+) -> Result<(), io::Error>
 where
     I: Iterator<Item = &'a D>,
     D: SearchableDocument + 'a,
@@ -167,9 +303,6 @@ where
         }
     }
 
-    let search_index_path = output_dir.join("search_index.json");
-    let index_file = File::create(&search_index_path)?;
-
     // Pre-compute IDF for every term so the value is only calculated once
     // regardless of how many pages contain the term.
     // IDF = ln((1 + N) / (1 + df)).  Evaluates to ≤ 0 when df == N (i.e. the
@@ -203,14 +336,26 @@ where
         })
         .collect();
 
-    serde_json::to_writer(index_file, &index)?;
+    output.write(Path::new("search_index.json"), &serde_json::to_vec(&index)?)?;
 
+    // Queries in languages other than English are stemmed by looking up the
+    // stem of each word.
+    let stems: BTreeMap<&str, &str> = pages
+        .iter()
+        .filter_map(|page| page.search_index())
+        .flat_map(|index| index.iter_words())
+        .map(|(word, stem)| (word.as_str(), stem.as_str()))
+        .collect();
+    if !stems.is_empty() {
+        output.write(Path::new(STEMS_FILE_NAME), &serde_json::to_vec(&stems)?)?;
+    }
     Ok(())
 }
 
 #[cfg(test)]
 pub mod test {
-    use super::TermFrequenciesBuilder;
+    use super::{write_search_indices, TermFrequenciesBuilder, TermFrequenciesIndex};
+    use crate::output::MemoryOutput;
 
     #[test]
     pub fn empty_search_indx() {
@@ -276,5 +421,78 @@ pub mod test {
             assert!(term.len() >= 3, "short term {:?} should not be indexed", term);
         }
     }
-}
 
+    #[test]
+    pub fn stemming_follows_language() {
+        let mut builder = TermFrequenciesBuilder::new("fr-CA");
+        builder.add_terms("Les chanteuses chantaient");
+        let index = builder.finalise();
+
+        // French has no stopwords here, and is stemmed with its own algorithm.
+        let mut terms: Vec<_> = index.iter_terms().cloned().collect();
+        terms.sort();
+        assert_eq!(vec!["chant", "chanteux", "le"], terms);
+        let words: Vec<_> = index.iter_words().collect();
+        assert!(words.contains(&(&String::from("chantaient"), &String::from("chant"))));
+
+        // Languages without a stemmer index whole words.
+        let mut builder = TermFrequenciesBuilder::new("ko");
+        builder.add_terms("한국어 사용자");
+        let index = builder.finalise();
+        assert_eq!(2, index.iter_terms().count());
+        assert_eq!(0, index.iter_words().count());
+    }
+
+    #[test]
+    pub fn cjk_text_indexed_as_bigrams() {
+        let mut builder = TermFrequenciesBuilder::new("ja");
+        builder.add_terms("Rustの日本語ドキュメント、語");
+        let index = builder.finalise();
+
+        let mut terms: Vec<_> = index.iter_terms().cloned().collect();
+        terms.sort();
+        assert_eq!(
+            vec![
+                "rust", "の日", "キュ", "ドキ", "メン", "ュメ", "ント", "日本", "本語", "語",
+                "語ド"
+            ],
+            terms
+        );
+        assert_eq!(0, index.iter_words().count());
+    }
+
+    struct Doc(TermFrequenciesIndex);
+
+    impl super::SearchableDocument for Doc {
+        fn title(&self) -> &str {
+            "Doc"
+        }
+
+        fn slug(&self) -> &str {
+            "doc"
+        }
+
+        fn search_index(&self) -> Option<&TermFrequenciesIndex> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    pub fn stems_written_for_other_languages() {
+        let output = MemoryOutput::new();
+        let mut builder = TermFrequenciesBuilder::new("de");
+        builder.add_terms("Häuser");
+        write_search_indices(&output, [Doc(builder.finalise())].iter()).unwrap();
+        assert_eq!(
+            "{\"häuser\":\"haus\"}",
+            String::from_utf8(output.get("search_stems.json").unwrap()).unwrap()
+        );
+
+        let output = MemoryOutput::new();
+        let mut builder = TermFrequenciesBuilder::default();
+        builder.add_terms("houses");
+        write_search_indices(&output, [Doc(builder.finalise())].iter()).unwrap();
+        assert!(output.get("search_index.json").is_some());
+        assert!(output.get("search_stems.json").is_none());
+    }
+}
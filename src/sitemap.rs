@@ -0,0 +1,144 @@
+//! Sitemap Generation
+//!
+//! When the site's public `base-url` is configured a `sitemap.xml` listing
+//! every rendered page is written to the root of the site, so search engines
+//! can find them all. A `robots.txt` pointing to the sitemap can be written
+//! alongside it. When several versions or languages are built each has its own
+//! sitemap, and `robots.txt` lists them all.
+
+use std::{io, path::Path, time::SystemTime};
+
+use crate::{output::OutputSink, utils};
+
+/// The name of the sitemap file written to the root of the site
+pub(crate) const SITEMAP_FILE_NAME: &str = "sitemap.xml";
+
+/// A Page in the Sitemap
+#[derive(Debug)]
+pub(crate) struct SitemapEntry {
+    /// Path to the page from the site root (e.g. `guide/intro/`)
+    pub path: String,
+    /// When the page was last changed, if known
+    pub modified: Option<SystemTime>,
+}
+
+/// Get the Absolute URL of a Page
+///
+/// Joins the page's `path` from the site root onto the `base_url`.
+pub(crate) fn absolute_url(base_url: &str, path: &str) -> String {
+    let mut url = base_url.trim_end_matches('/').to_owned();
+    url.push('/');
+    url.push_str(&utils::url_encode_path(Path::new(path)));
+    if !path.is_empty() && !url.ends_with('/') {
+        url.push('/');
+    }
+    url
+}
+
+/// Write the Sitemap
+///
+/// Writes `sitemap.xml` listing each of the `pages` at the given `base_url`.
+pub(crate) fn write_sitemap(
+    output: &dyn OutputSink,
+    base_url: &str,
+    pages: &[SitemapEntry],
+) -> io::Result<()> {
+    let mut sitemap = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for page in pages {
+        sitemap.push_str("  <url>\n");
+        sitemap.push_str(&format!(
+            "    <loc>{}</loc>\n",
+            utils::escape_html(absolute_url(base_url, &page.path))
+        ));
+        if let Some(modified) = page.modified.and_then(utils::format_date) {
+            sitemap.push_str(&format!("    <lastmod>{}</lastmod>\n", modified));
+        }
+        sitemap.push_str("  </url>\n");
+    }
+    sitemap.push_str("</urlset>\n");
+    output.write(Path::new(SITEMAP_FILE_NAME), sitemap.as_bytes())
+}
+
+/// Write `robots.txt`
+///
+/// Allows every page to be crawled, and links to each of the `sitemaps` by
+/// their absolute URLs.
+pub(crate) fn write_robots(output: &dyn OutputSink, sitemaps: &[String]) -> io::Result<()> {
+    let mut robots = String::from("User-agent: *\nAllow: /\n\n");
+    for sitemap in sitemaps {
+        robots.push_str(&format!("Sitemap: {}\n", sitemap));
+    }
+    output.write(Path::new("robots.txt"), robots.as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::output::MemoryOutput;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn absolute_page_urls() {
+        assert_eq!(
+            "https://example.com/docs/",
+            absolute_url("https://example.com/docs/", "")
+        );
+        assert_eq!(
+            "https://example.com/guide/caf%C3%A9/",
+            absolute_url("https://example.com", "guide/café/")
+        );
+    }
+
+    #[test]
+    fn write_sitemap_and_robots() {
+        let output = MemoryOutput::new();
+        let pages = vec![
+            SitemapEntry {
+                path: String::new(),
+                modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            },
+            SitemapEntry {
+                path: String::from("guide/"),
+                modified: None,
+            },
+        ];
+        write_sitemap(&output, "https://example.com/", &pages).unwrap();
+        write_robots(&output, &[String::from("https://example.com/sitemap.xml")]).unwrap();
+
+        let sitemap = String::from_utf8(output.get("sitemap.xml").unwrap()).unwrap();
+        assert!(sitemap.contains(
+            "<url>\n    <loc>https://example.com/</loc>\n    <lastmod>2023-11-14</lastmod>\n  </url>"
+        ));
+        assert!(sitemap.contains("<url>\n    <loc>https://example.com/guide/</loc>\n  </url>"));
+        assert_eq!(
+            "User-agent: *\nAllow: /\n\nSitemap: https://example.com/sitemap.xml\n",
+            String::from_utf8(output.get("robots.txt").unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn robots_is_optional() {
+        let output = MemoryOutput::new();
+        write_sitemap(&output, "https://example.com/", &[]).unwrap();
+        assert!(output.get("sitemap.xml").is_some());
+        assert!(output.get("robots.txt").is_none());
+    }
+
+    #[test]
+    fn robots_lists_each_sitemap() {
+        let output = MemoryOutput::new();
+        let sitemaps = [
+            String::from("https://example.com/en/sitemap.xml"),
+            String::from("https://example.com/ja/sitemap.xml"),
+        ];
+        write_robots(&output, &sitemaps).unwrap();
+        assert_eq!(
+            "User-agent: *\nAllow: /\n\nSitemap: https://example.com/en/sitemap.xml\n\
+             Sitemap: https://example.com/ja/sitemap.xml\n",
+            String::from_utf8(output.get("robots.txt").unwrap()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Documentation Source
+//!
+//! This module models the place a documentation set is read from. The source
+//! bundles together the root directory of the documentation and the site
+//! configuration loaded from it. It is threaded through the doctree as it is
+//! opened, and on into rendering. Files are read through the source's
+//! `SourceFs`, so documentation doesn't have to come from the local disk.
+
+mod fs;
+
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+use log::warn;
+
+use crate::{
+    config::{self, Config},
+    diagnostics::{Diagnostic, Diagnostics},
+    diagram::DiagramCache,
+    error::Result,
+    git::GitHistory,
+    highlight::{self, Highlighter},
+};
+
+pub use fs::{DiskFs, MemoryFs, Metadata, SourceFs};
+
+/// Documentation Source
+///
+/// Represents the root of a documentation set along with its configuration.
+pub(crate) struct Source {
+    /// The root directory of the documentation
+    root: PathBuf,
+    /// The file system the documentation is read from
+    fs: Box<dyn SourceFs>,
+    /// The site configuration
+    config: Config,
+    /// The git history of the documentation, if enabled
+    history: Option<GitHistory>,
+    /// Extra stylesheets to include in every page
+    extra_css: Vec<PathBuf>,
+    /// Extra scripts to include in every page
+    extra_js: Vec<PathBuf>,
+    /// The highlighter to use for code blocks, if not the default
+    highlighter: Option<Rc<dyn Highlighter>>,
+    /// The language the documentation is written in
+    default_lang: String,
+    /// The translation being read, if not the default language
+    translation: Option<String>,
+    /// Problems found with the documentation so far
+    diagnostics: Diagnostics,
+    /// Diagrams drawn so far
+    diagrams: DiagramCache,
+    /// Paths left out of the site, made absolute
+    excluded: Vec<PathBuf>,
+}
+
+impl Source {
+    /// Open a Source
+    ///
+    /// Loads the site configuration from the given `root` directory within
+    /// the file system `fs`.
+    pub fn open<P: AsRef<Path>>(root: P, fs: Box<dyn SourceFs>) -> Result<Self> {
+        Self::open_at(root, fs, None)
+    }
+
+    /// Open a Source at a Git Revision
+    ///
+    /// As `open`, but any git history is read from `rev` rather than the
+    /// current `HEAD`. Used for versions of the documentation read from git.
+    pub fn open_at<P: AsRef<Path>>(
+        root: P,
+        fs: Box<dyn SourceFs>,
+        rev: Option<&str>,
+    ) -> Result<Self> {
+        let config = Config::load(fs.as_ref(), root.as_ref())?;
+        let history = if config.git.enabled() {
+            GitHistory::load(&root, rev)
+                .map_err(|e| warn!("Could not read git history, using file times: {}", e))
+                .ok()
+        } else {
+            None
+        };
+        let root = root.as_ref().to_owned();
+        let default_lang = config.lang.clone();
+        let mut source = Source {
+            root,
+            fs,
+            config,
+            history,
+            extra_css: Vec::new(),
+            extra_js: Vec::new(),
+            highlighter: None,
+            default_lang,
+            translation: None,
+            diagnostics: Diagnostics::default(),
+            diagrams: DiagramCache::default(),
+            excluded: Vec::new(),
+        };
+        source.extra_css = find_extras(&source, source.config.extra_css.as_deref(), "extra.css");
+        source.extra_js = find_extras(&source, source.config.extra_js.as_deref(), "extra.js");
+        Ok(source)
+    }
+
+    /// Get the root directory of this source
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the file system the documentation is read from
+    pub fn fs(&self) -> &dyn SourceFs {
+        self.fs.as_ref()
+    }
+
+    /// Get the site configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Get the mutable site configuration
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// Get the Highlighter
+    ///
+    /// Returns the highlighter set for this source, or the default highlighter
+    /// for the configuration.
+    pub fn highlighter(&self) -> &dyn Highlighter {
+        match &self.highlighter {
+            Some(highlighter) => highlighter.as_ref(),
+            None => highlight::get_hilighter(&self.config),
+        }
+    }
+
+    /// Set the highlighter to use for code blocks
+    pub fn set_highlighter(&mut self, highlighter: Rc<dyn Highlighter>) {
+        self.highlighter = Some(highlighter);
+    }
+
+    /// Get the highlighter set for this source, if any, to share with another
+    pub fn custom_highlighter(&self) -> Option<Rc<dyn Highlighter>> {
+        self.highlighter.clone()
+    }
+
+    /// Get the language the documentation is written in
+    pub fn default_lang(&self) -> &str {
+        &self.default_lang
+    }
+
+    /// Get the translation being read, if not the default language
+    pub fn translation(&self) -> Option<&str> {
+        self.translation.as_deref()
+    }
+
+    /// Set the Translation to Read
+    ///
+    /// Pages are read in the language with the given `code`, or the default
+    /// language if `None`. The configured `lang` is updated to match.
+    pub fn set_translation(&mut self, code: Option<&str>) {
+        self.translation = code.map(str::to_owned);
+        self.config.lang = code.unwrap_or(&self.default_lang).to_owned();
+    }
+
+    /// Report a problem found with the documentation
+    pub fn report(&self, diagnostic: Diagnostic) {
+        self.diagnostics.report(diagnostic);
+    }
+
+    /// Take the problems reported so far
+    pub fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.take()
+    }
+
+    /// Get the cache of diagrams drawn while rendering this source
+    pub fn diagrams(&self) -> &DiagramCache {
+        &self.diagrams
+    }
+
+    /// Set the cache of diagrams, to share it with other builds
+    pub fn set_diagrams(&mut self, diagrams: DiagramCache) {
+        self.diagrams = diagrams;
+    }
+
+    /// Leave the File or Directory at `path` Out of the Site
+    ///
+    /// The `path` is resolved against the current directory, as with the
+    /// documentation root.
+    pub fn exclude(&mut self, path: &Path) {
+        if let Ok(path) = std::path::absolute(path) {
+            self.excluded.push(path);
+        }
+    }
+
+    /// Check if `path` has been left out of the site
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        !self.excluded.is_empty()
+            && std::path::absolute(path).is_ok_and(|path| {
+                self.excluded
+                    .iter()
+                    .any(|excluded| path.starts_with(excluded))
+            })
+    }
+
+    /// Get the extra stylesheets to include in every page
+    pub fn extra_css(&self) -> &[PathBuf] {
+        &self.extra_css
+    }
+
+    /// Get the extra scripts to include in every page
+    pub fn extra_js(&self) -> &[PathBuf] {
+        &self.extra_js
+    }
+
+    /// Get the Path of an Extra Asset Within the Site
+    ///
+    /// Extras keep their path relative to the source root, so references
+    /// between them still work once copied. Extras from outside the source
+    /// root are put at the root of the site instead.
+    pub fn extra_site_path(&self, extra: &Path) -> PathBuf {
+        let relative = self.relative_path(extra);
+        if relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            relative.to_owned()
+        } else {
+            PathBuf::from(extra.file_name().unwrap_or_default())
+        }
+    }
+
+    /// Get the Path Relative to the Source Root
+    ///
+    /// Strips the root directory from the given `path`. Paths which are
+    /// outside the source root are returned unchanged.
+    pub fn relative_path<'p>(&self, path: &'p Path) -> &'p Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    /// Get the Last-Modified Time of a File
+    ///
+    /// If enabled, the time of the last commit to the file is used. Otherwise,
+    /// or if the file has no history, the file system time is used instead.
+    pub fn modified(&self, path: &Path) -> Option<SystemTime> {
+        self.history
+            .as_ref()
+            .filter(|_| self.config.git.last_updated)
+            .and_then(|history| history.file(self.relative_path(path)))
+            .and_then(|file| file.modified)
+            .or_else(|| self.fs.metadata(path).ok().and_then(|m| m.modified))
+    }
+
+    /// Get the Contributors to a File
+    ///
+    /// Returns the authors of the commits to the file, if enabled.
+    pub fn authors(&self, path: &Path) -> &[String] {
+        self.history
+            .as_ref()
+            .filter(|_| self.config.git.contributors)
+            .and_then(|history| history.file(self.relative_path(path)))
+            .map(|file| &file.authors[..])
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Source")
+            .field("root", &self.root)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Find Extra Assets
+///
+/// Resolves the `configured` extra assets against the `source` root. Any that
+/// don't exist are reported and skipped. If none were configured then the
+/// `default` file is used, if it exists.
+fn find_extras(source: &Source, configured: Option<&[PathBuf]>, default: &str) -> Vec<PathBuf> {
+    let root = source.root();
+    match configured {
+        Some(paths) => paths
+            .iter()
+            .filter(|path| {
+                let exists = source.fs().is_file(&root.join(path));
+                if !exists {
+                    source.report(
+                        Diagnostic::warning(format!(
+                            "extra asset `{}` does not exist",
+                            path.display()
+                        ))
+                        .in_file(root.join(config::CONFIG_FILE_NAME)),
+                    );
+                }
+                exists
+            })
+            .map(|path| root.join(path))
+            .collect(),
+        None => Some(root.join(default))
+            .filter(|path| source.fs().is_file(path))
+            .into_iter()
+            .collect(),
+    }
+}
@@ -0,0 +1,236 @@
+//! Source File Systems
+//!
+//! Documentation is read through the `SourceFs` trait rather than directly
+//! from `std::fs`. This allows a documentation set to come from somewhere
+//! other than the local disk, such as an in-memory map of files. Paths are
+//! always given to the file system in full, that is with the documentation
+//! root as a prefix.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Source File System Trait
+///
+/// Provides read-only access to the files that make up a documentation set.
+pub trait SourceFs {
+    /// List the Entries in a Directory
+    ///
+    /// Returns the full path of each file and directory directly within the
+    /// directory at `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Read the Contents of a File
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Get the Metadata for a File or Directory
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Read the Contents of a File as a String
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Check if the given `path` is a directory
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path).map(|m| m.is_dir).unwrap_or(false)
+    }
+
+    /// Check if the given `path` is a file
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path).map(|m| !m.is_dir).unwrap_or(false)
+    }
+}
+
+/// File Metadata
+///
+/// The information docket needs about a single source file or directory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metadata {
+    /// True if the path refers to a directory
+    pub is_dir: bool,
+    /// The time the file was last modified, if known
+    pub modified: Option<SystemTime>,
+}
+
+/// Disk File System
+///
+/// Reads documentation from the local disk with `std::fs`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DiskFs;
+
+impl SourceFs for DiskFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(Metadata {
+            is_dir: metadata.is_dir(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// In-Memory File System
+///
+/// Holds the contents of each file in a map keyed by path. Directories are
+/// implied by the paths of the files within them.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFs(BTreeMap<PathBuf, Vec<u8>>);
+
+impl MemoryFs {
+    /// Create an Empty File System
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a file at the given `path`, replacing any existing contents
+    pub fn insert<P, C>(&mut self, path: P, contents: C)
+    where
+        P: Into<PathBuf>,
+        C: Into<Vec<u8>>,
+    {
+        self.0.insert(path.into(), contents.into());
+    }
+
+    /// Iterate over the paths of files strictly within `dir`
+    fn files_within<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = &'a Path> + 'a {
+        self.0.keys().filter_map(move |path| {
+            path.strip_prefix(dir)
+                .ok()
+                .filter(|rest| !rest.as_os_str().is_empty())
+        })
+    }
+}
+
+impl<P, C> FromIterator<(P, C)> for MemoryFs
+where
+    P: Into<PathBuf>,
+    C: Into<Vec<u8>>,
+{
+    fn from_iter<I: IntoIterator<Item = (P, C)>>(iter: I) -> Self {
+        let mut fs = MemoryFs::new();
+        for (path, contents) in iter {
+            fs.insert(path, contents);
+        }
+        fs
+    }
+}
+
+impl SourceFs for MemoryFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let entries: BTreeSet<_> = self
+            .files_within(path)
+            .filter_map(|rest| rest.components().next())
+            .map(|first| path.join(first))
+            .collect();
+        if entries.is_empty() {
+            return Err(not_found(path));
+        }
+        Ok(entries.into_iter().collect())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.0.get(path).cloned().ok_or_else(|| not_found(path))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let is_dir = if self.0.contains_key(path) {
+            false
+        } else if self.files_within(path).next().is_some() {
+            true
+        } else {
+            return Err(not_found(path));
+        };
+        Ok(Metadata {
+            is_dir,
+            modified: None,
+        })
+    }
+}
+
+#[cfg(test)]
+impl MemoryFs {
+    /// Create a File System Holding a Documentation Set
+    ///
+    /// Adds each of the `files`, and a `docs/docket.toml` with the given
+    /// `config`.
+    pub(crate) fn with_config(config: &str, files: &[(&str, &str)]) -> Self {
+        let mut fs: MemoryFs = files.iter().copied().collect();
+        fs.insert("docs/docket.toml", config);
+        fs
+    }
+}
+
+/// Create an error for a missing path
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{:?} not found", path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn example() -> MemoryFs {
+        [
+            ("docs/index.md", "# Home"),
+            ("docs/guide/intro.md", "# Intro"),
+            ("docs/guide/img/logo.png", "PNG"),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn memory_fs_read_dir() {
+        let fs = example();
+        assert_eq!(
+            vec![PathBuf::from("docs/guide"), PathBuf::from("docs/index.md")],
+            fs.read_dir(Path::new("docs")).unwrap()
+        );
+        assert_eq!(
+            vec![
+                PathBuf::from("docs/guide/img"),
+                PathBuf::from("docs/guide/intro.md")
+            ],
+            fs.read_dir(Path::new("docs/guide")).unwrap()
+        );
+        assert!(fs.read_dir(Path::new("docs/missing")).is_err());
+    }
+
+    #[test]
+    fn memory_fs_metadata() {
+        let fs = example();
+        assert!(fs.is_dir(Path::new("docs")));
+        assert!(fs.is_dir(Path::new("docs/guide/img")));
+        assert!(fs.is_file(Path::new("docs/guide/intro.md")));
+        assert!(!fs.is_file(Path::new("docs/guide")));
+        assert!(!fs.is_dir(Path::new("docs/gui")));
+        assert!(fs.metadata(Path::new("docs/other.md")).is_err());
+    }
+
+    #[test]
+    fn memory_fs_read() {
+        let fs = example();
+        assert_eq!(
+            "# Intro",
+            fs.read_to_string(Path::new("docs/guide/intro.md")).unwrap()
+        );
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            fs.read(Path::new("docs/guide")).unwrap_err().kind()
+        );
+    }
+}
@@ -4,15 +4,16 @@
 //! tree can be rendered into HTML with `pulldown`, or quieried for the document
 //! layout in order to produce navigation elements.
 
-use std::{borrow::Borrow, iter::Peekable};
+use std::{borrow::Borrow, collections::HashSet, iter::Peekable, ops::Range};
 
-use log::error;
 use pulldown_cmark::*;
 
 use crate::{
-    highlight,
+    config::{Config, SlugConfig},
+    diagnostics::{Diagnostic, Position},
+    diagram::{self, DiagramCache},
+    highlight::Highlighter,
     search::{TermFrequenciesBuilder, TermFrequenciesIndex},
-    utils,
 };
 
 /// # A single ement in the TOC
@@ -126,21 +127,40 @@ impl<'a> Nodes<'a> {
 /// A tree can be queried for information about the document's outline, primary
 /// heading, or full contnet. The layout module uses the public API of the `Toc`
 /// to render out page's contents, internal navigation, and title information.
+///
+/// Any problems found while parsing the markdown are kept alongside the tree
+/// as diagnostics, positioned within the markdown source.
 #[derive(Debug)]
-pub(crate) struct Toc(Vec<TocElement>, TermFrequenciesIndex);
+pub(crate) struct Toc(Vec<TocElement>, TermFrequenciesIndex, Vec<Diagnostic>);
 
 impl Toc {
     /// # Parse a Tree of Contents
     ///
     /// Given a markdown string parse it and return a vector containing the
-    /// top-level elements in the document's tree.
-    pub fn new(markdown: &str) -> Self {
-        let parser = Parser::new_ext(markdown, Options::all());
-        let mut index_builder = TermFrequenciesBuilder::default();
+    /// top-level elements in the document's tree. Code blocks are drawn as
+    /// diagrams, using the `diagrams` cache, or highlighted with the given
+    /// `highlighter`.
+    pub fn new(
+        markdown: &str,
+        config: &Config,
+        highlighter: &dyn Highlighter,
+        diagrams: &DiagramCache,
+    ) -> Self {
+        let parser = Parser::new_ext(markdown, Options::all()).into_offset_iter();
+        let mut index_builder = TermFrequenciesBuilder::new(&config.lang);
+        let mut warnings = Vec::new();
         let parser = build_search_index(&mut index_builder, parser);
-        let parser = hl_codeblocks(parser);
-        let events = parse_toc_events(parser);
-        Toc(events, index_builder.finalise())
+        let parser = hl_codeblocks(parser, config, highlighter, diagrams, &mut warnings);
+        let (events, toc_warnings) = parse_toc_events(parser, &config.slugs);
+        warnings.extend(toc_warnings);
+        warnings.sort();
+        let diagnostics = warnings
+            .into_iter()
+            .map(|(offset, message)| {
+                Diagnostic::warning(message).at(Position::from_offset(markdown, offset))
+            })
+            .collect();
+        Toc(events, index_builder.finalise(), diagnostics)
     }
 
     /// # Primary Heading
@@ -179,44 +199,83 @@ impl Toc {
     pub fn search_index(&self) -> &TermFrequenciesIndex {
         &self.1
     }
+
+    /// # Take the Diagnostics
+    ///
+    /// Removes the problems found while parsing the markdown from the tree.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.2)
+    }
 }
 
-fn hl_codeblocks<'a, I>(parser: I) -> impl Iterator<Item = Event<'a>>
+/// Replace code blocks with diagrams or highlighted HTML. Diagrams which can't
+/// be drawn, code blocks in a language the highlighter doesn't know, and code
+/// blocks containing anything other than text, are added to `warnings` along
+/// with their offset within the markdown.
+/// Replacement events are given the range of the code block's end.
+fn hl_codeblocks<'a, 'p, I>(
+    parser: I,
+    config: &'a Config,
+    hl: &'a dyn Highlighter,
+    diagrams: &'a DiagramCache,
+    warnings: &'p mut Vec<(usize, String)>,
+) -> impl Iterator<Item = (Event<'a>, Range<usize>)> + 'p
 where
-    I: Iterator<Item = Event<'a>>,
+    'a: 'p,
+    I: Iterator<Item = (Event<'a>, Range<usize>)> + 'p,
 {
-    let mut state: Option<String> = None;
-    let hl = highlight::get_hilighter();
-    parser.flat_map(move |event| {
-        if let Some(mut hl_state) = state.take() {
+    let mut state: Option<(String, usize)> = None;
+    parser.flat_map(move |(event, range)| {
+        if let Some((mut hl_state, start)) = state.take() {
             match event {
                 Event::Text(txt) => {
                     hl_state.push_str(txt.as_ref());
-                    state = Some(hl_state);
+                    state = Some((hl_state, start));
                     vec![]
                 }
                 Event::End(Tag::CodeBlock(kind)) => {
                     state = None;
-                    hl.hl_codeblock(
-                        match &kind {
-                            CodeBlockKind::Indented => None,
-                            CodeBlockKind::Fenced(name) => Some(name.as_ref()),
-                        },
-                        &hl_state,
-                    )
+                    let name = match &kind {
+                        CodeBlockKind::Indented => None,
+                        CodeBlockKind::Fenced(name) => Some(name.as_ref()),
+                    };
+                    // Diagrams are drawn in place of the code block. Anything
+                    // we can't draw falls through to the highlighter.
+                    let diagram = name.and_then(|name| {
+                        diagram::render_diagram(config, diagrams, name, &hl_state)
+                    });
+                    let is_diagram = diagram.is_some();
+                    match diagram {
+                        Some(Ok(html)) => return vec![(Event::Html(html.into()), range)],
+                        Some(Err(message)) => warnings.push((start, message)),
+                        None => {}
+                    }
+                    if let Some(lang) = name.and_then(|name| name.split_whitespace().next()) {
+                        if !is_diagram && !hl.supports(lang) {
+                            warnings
+                                .push((start, format!("unknown code block language `{}`", lang)));
+                        }
+                    }
+                    hl.hl_codeblock(name, &hl_state)
+                        .into_iter()
+                        .map(|event| (event, range.clone()))
+                        .collect()
                 }
                 _ => {
-                    error!("Unexpected item in codeblock: {:?}", event);
-                    vec![event]
+                    warnings.push((
+                        range.start,
+                        format!("unexpected item in code block: {:?}", event),
+                    ));
+                    vec![(event, range)]
                 }
             }
         } else {
             match event {
                 Event::Start(Tag::CodeBlock(_)) => {
-                    state = Some(String::new());
+                    state = Some((String::new(), range.start));
                     vec![]
                 }
-                _ => vec![event],
+                _ => vec![(event, range)],
             }
         }
     })
@@ -225,11 +284,11 @@ where
 fn build_search_index<'a, 'p, I>(
     index_builder: &'p mut TermFrequenciesBuilder,
     parser: I,
-) -> impl Iterator<Item = Event<'a>> + 'p
+) -> impl Iterator<Item = (Event<'a>, Range<usize>)> + 'p
 where
-    I: Iterator<Item = Event<'a>> + 'p,
+    I: Iterator<Item = (Event<'a>, Range<usize>)> + 'p,
 {
-    parser.inspect(|event| match event {
+    parser.inspect(|(event, _)| match event {
         Event::Code(code) => {
             index_builder.add_terms(&code);
         }
@@ -280,26 +339,92 @@ fn drain_events_to_html(events: &mut Vec<Event>) -> Option<String> {
     }
 }
 
-/// Parse a TOC tree from the headers in the markdown document
-fn parse_toc_events<'a, I>(events: I) -> Vec<TocElement>
+/// Heading Slugs
+///
+/// Tracks the slugs already given to headings within a page, so that each
+/// heading's `id` is unique.
+struct Slugs<'c> {
+    /// The policy used to make slugs from heading text
+    policy: &'c SlugConfig,
+    /// The slugs used so far
+    used: HashSet<String>,
+    /// Explicit ids given anywhere in the page, which generated slugs avoid
+    reserved: HashSet<String>,
+    /// Explicit ids which collide, and the offset of their heading
+    warnings: Vec<(usize, String)>,
+}
+
+impl<'c> Slugs<'c> {
+    fn new(policy: &'c SlugConfig, reserved: HashSet<String>) -> Self {
+        Slugs {
+            policy,
+            used: HashSet::new(),
+            reserved,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Get a Unique Slug for a Heading
+    ///
+    /// Explicit `{#id}` fragments are kept as written, with a warning if they
+    /// are already in use. Slugs generated from the heading's `text` are given
+    /// a numeric suffix instead, e.g. `example`, `example-1`, and never take
+    /// an explicit id, even one from a later heading.
+    fn unique(&mut self, explicit: Option<&str>, text: &str, offset: usize) -> String {
+        if let Some(id) = explicit {
+            if !self.used.insert(id.to_owned()) {
+                self.warnings
+                    .push((offset, format!("duplicate heading id `{}`", id)));
+            }
+            return id.to_owned();
+        }
+
+        let slug = self.policy.slugify(text);
+        let mut unique = slug.clone();
+        let mut suffix = 1;
+        while self.reserved.contains(&unique) || !self.used.insert(unique.clone()) {
+            unique = format!("{}-{}", slug, suffix);
+            suffix += 1;
+        }
+        unique
+    }
+}
+
+/// Parse a TOC tree from the headers in the markdown document. Returns the
+/// tree along with any warnings about the headings' ids.
+fn parse_toc_events<'a, I>(
+    events: I,
+    policy: &SlugConfig,
+) -> (Vec<TocElement>, Vec<(usize, String)>)
 where
-    I: Iterator<Item = Event<'a>>,
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
 {
-    parse_toc_at_level(None, &mut events.peekable())
+    let events: Vec<_> = events.collect();
+    let reserved = events
+        .iter()
+        .filter_map(|(event, _)| match event {
+            Event::End(Tag::Heading(_, Some(id), _)) => Some((*id).to_owned()),
+            _ => None,
+        })
+        .collect();
+    let mut slugs = Slugs::new(policy, reserved);
+    let elements = parse_toc_at_level(None, &mut events.into_iter().peekable(), &mut slugs);
+    (elements, slugs.warnings)
 }
 
 /// Parse the toc tree at a given header level.
 fn parse_toc_at_level<'a, I>(
     level: Option<HeadingLevel>,
     events: &mut Peekable<I>,
+    slugs: &mut Slugs,
 ) -> Vec<TocElement>
 where
-    I: Iterator<Item = Event<'a>>,
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
 {
     let mut buffered = Vec::new();
     let mut elements = Vec::new();
 
-    while let Some(event) = events.next_if(|event| is_below(level, event)) {
+    while let Some((event, range)) = events.next_if(|(event, _)| is_below(level, event)) {
         match event {
             // If we see a heading tag then start building a heading
             Event::Start(Tag::Heading(..)) => {
@@ -313,16 +438,14 @@ where
                 // Not we didn't push the opening event _and_ we ignore the
                 // closing one here too. This means we will only render the
                 // _contents_ of the header, not the opening and closing tags.
-                let slug = frag
-                    .map(|s| s.to_owned())
-                    .unwrap_or_else(|| utils::slugify(&events_to_plain(buffered.iter())));
+                let slug = slugs.unique(frag, &events_to_plain(buffered.iter()), range.start);
                 elements.push(TocElement::Node(TocNode {
                     heading: Heading {
                         level,
                         contents: drain_events_to_html(&mut buffered).unwrap_or(String::new()),
                         slug,
                     },
-                    contents: parse_toc_at_level(Some(level), events),
+                    contents: parse_toc_at_level(Some(level), events, slugs),
                 }))
             }
             // If we see a closing paragraph then check if we're looking at
@@ -396,8 +519,10 @@ fn is_below(level: Option<HeadingLevel>, event: &Event) -> bool {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{asset::Asset, diagram::DiagramCache, highlight};
+
     fn h(level: HeadingLevel, contents: &str) -> Heading {
-        let slug = utils::slugify(&contents);
+        let slug = SlugConfig::default().slugify(contents);
         hslug(level, contents, &slug)
     }
 
@@ -410,7 +535,14 @@ mod test {
     }
 
     fn parse_toc(s: &str) -> Vec<TocElement> {
-        Toc::new(s).into_inner()
+        let config = Config::default();
+        Toc::new(
+            s,
+            &config,
+            highlight::get_hilighter(&config),
+            &DiagramCache::default(),
+        )
+        .into_inner()
     }
 
     #[test]
@@ -441,7 +573,7 @@ block four
 
 > last bit",
         );
-        let toc = parse_toc_events(parser);
+        let (toc, _) = parse_toc_events(parser.into_offset_iter(), &SlugConfig::default());
 
         assert_eq!(2, toc.len());
     }
@@ -451,7 +583,7 @@ block four
         let doc = "hello world";
         let parser = Parser::new(doc);
 
-        let toc = parse_toc_events(parser);
+        let (toc, _) = parse_toc_events(parser.into_offset_iter(), &SlugConfig::default());
 
         assert_eq!(vec![TocElement::Html("<p>hello world</p>\n".into())], toc);
     }
@@ -482,7 +614,7 @@ block four
                 heading: hslug(
                     HeadingLevel::H1,
                     "I am <code>an</code> <strong>H1</strong>",
-                    "I-am-an-H1"
+                    "i-am-an-h1"
                 ),
                 contents: Vec::new()
             })],
@@ -541,4 +673,130 @@ block four
             toc
         )
     }
+
+    #[test]
+    fn parse_with_duplicate_headings() {
+        let doc = "# Example\n\n## Example\n\n## Example\n\n## Example 1\n";
+        let slugs: Vec<_> = Toc::new(
+            doc,
+            &Config::default(),
+            &highlight::PlainHighlighter,
+            &DiagramCache::default(),
+        )
+        .walk_elements()
+        .filter_map(|element| match element {
+            TocElement::Node(node) => Some(node.heading.slug.clone()),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(
+            vec!["example", "example-1", "example-2", "example-1-1"],
+            slugs
+        );
+    }
+
+    #[test]
+    fn warn_on_duplicate_explicit_ids() {
+        let doc = "# Intro {#start}\n\n## Start\n\n## Again {#start}\n";
+        let mut toc = Toc::new(
+            doc,
+            &Config::default(),
+            &highlight::PlainHighlighter,
+            &DiagramCache::default(),
+        );
+        let slugs: Vec<_> = toc
+            .walk_elements()
+            .filter_map(|element| match element {
+                TocElement::Node(node) => Some(node.heading.slug.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec!["start", "start-1", "start"], slugs);
+
+        let diagnostics = toc.take_diagnostics();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("duplicate heading id `start`", diagnostics[0].message());
+        assert_eq!(
+            Some(Position { line: 5, column: 1 }),
+            diagnostics[0].position()
+        );
+    }
+
+    #[test]
+    fn explicit_ids_take_precedence() {
+        let doc = "# Start\n\n## Start\n\n## Intro {#start}\n";
+        let mut toc = Toc::new(
+            doc,
+            &Config::default(),
+            &highlight::PlainHighlighter,
+            &DiagramCache::default(),
+        );
+        let slugs: Vec<_> = toc
+            .walk_elements()
+            .filter_map(|element| match element {
+                TocElement::Node(node) => Some(node.heading.slug.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec!["start-1", "start-2", "start"], slugs);
+        assert!(toc.take_diagnostics().is_empty());
+    }
+
+    /// A highlighter which knows only Rust
+    struct RustOnly;
+
+    impl Highlighter for RustOnly {
+        fn hl_codeblock(&self, name: Option<&str>, block: &str) -> Vec<Event<'_>> {
+            highlight::PlainHighlighter.hl_codeblock(name, block)
+        }
+
+        fn supports(&self, lang: &str) -> bool {
+            lang == "rust"
+        }
+
+        fn assets(&self) -> std::io::Result<Vec<Asset>> {
+            Ok(Vec::new())
+        }
+
+        fn write_header(&self, _out: &mut dyn std::io::Write, _root: &str) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn warn_on_unknown_languages() {
+        let doc = "# Code\n\n```rust\nfn main() {}\n```\n\n  ```pyhton extra\n  pass\n  ```\n\n    indented\n";
+        let mut toc = Toc::new(doc, &Config::default(), &RustOnly, &DiagramCache::default());
+        let diagnostics = toc.take_diagnostics();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            "unknown code block language `pyhton`",
+            diagnostics[0].message()
+        );
+        assert_eq!(
+            Some(Position { line: 7, column: 3 }),
+            diagnostics[0].position()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn warn_on_failed_diagrams() {
+        let mut config = Config::default();
+        config.diagrams.insert("dot".into(), "false".into());
+        let doc = "# Diagram\n\n```dot\ndigraph { a -> b }\n```\n";
+        let mut toc = Toc::new(doc, &config, &RustOnly, &DiagramCache::default());
+        let diagnostics = toc.take_diagnostics();
+        assert_eq!(1, diagnostics.len());
+        assert!(diagnostics[0]
+            .message()
+            .starts_with("could not render `dot` diagram with `false`"));
+        assert_eq!(
+            Some(Position { line: 3, column: 1 }),
+            diagnostics[0].position()
+        );
+        assert!(toc
+            .walk_elements()
+            .any(|element| matches!(element, TocElement::Html(html) if html.contains("<pre>"))));
+    }
 }
@@ -0,0 +1,348 @@
+//! Translations
+//!
+//! Documentation can be published in several languages from one source. The
+//! languages are listed in the `[languages]` table of `docket.toml`. A page is
+//! translated by adding a file alongside it with the language's tag before the
+//! extension, such as `setup.ja.md` for `setup.md`, or by adding it to a
+//! directory in the documentation root named after the language, such as
+//! `ja/setup.md`. Each language's tree mirrors the default language's, with
+//! the translated pages in place of the originals.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{source::Source, utils};
+
+/// A Language the Documentation is Published In
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Language {
+    /// The language's tag, which is also the directory it is rendered to
+    pub code: String,
+    /// The name of the language, as shown to readers
+    pub name: String,
+}
+
+/// List the Languages
+///
+/// Returns each configured language, starting with the default. If no
+/// languages are configured the documentation isn't translated, and the list
+/// is empty.
+pub(crate) fn languages(source: &Source) -> Vec<Language> {
+    let languages = &source.config().languages;
+    if languages.is_empty() {
+        return Vec::new();
+    }
+    let default = source.default_lang();
+    std::iter::once(default)
+        .chain(
+            languages
+                .keys()
+                .map(String::as_str)
+                .filter(|c| *c != default),
+        )
+        .map(|code| Language {
+            code: code.to_owned(),
+            name: languages.get(code).map_or(code, String::as_str).to_owned(),
+        })
+        .collect()
+}
+
+/// Check if `code` is the tag of one of the translations
+fn is_translation(source: &Source, code: &str) -> bool {
+    code != source.default_lang() && source.config().languages.contains_key(code)
+}
+
+/// Split a Translation's Path
+///
+/// If `path` is a translation, such as `setup.ja.md`, returns the path of the
+/// page it translates, `setup.md`, and the translation's tag.
+pub(crate) fn split_translation(source: &Source, path: &Path) -> Option<(PathBuf, String)> {
+    let stem = path.file_stem()?.to_str()?;
+    let (original, code) = stem.rsplit_once('.')?;
+    if original.is_empty() || !is_translation(source, code) {
+        return None;
+    }
+    let mut name = OsString::from(original);
+    if let Some(ext) = path.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    Some((path.with_file_name(name), code.to_owned()))
+}
+
+/// Get the Normalised Stem of a Path
+///
+/// As `utils::normalised_stem`, except that a translation has the same stem
+/// as the page it translates.
+pub(crate) fn normalised_stem(source: &Source, path: &Path) -> Option<String> {
+    match split_translation(source, path) {
+        Some((original, _)) => utils::normalised_stem(original),
+        None => utils::normalised_stem(path),
+    }
+}
+
+/// Get a Path within the Tree
+///
+/// Strips the documentation root, or the root directory of the translation
+/// being read, from `path`. The root of the tree is the empty path.
+pub(crate) fn tree_path<'p>(source: &Source, path: &'p Path) -> &'p Path {
+    source
+        .translation()
+        .and_then(|code| path.strip_prefix(source.root().join(code)).ok())
+        .unwrap_or_else(|| source.relative_path(path))
+}
+
+/// Check if `path` is the root directory of a translation, such as `ja/`
+fn is_translation_root(source: &Source, path: &Path) -> bool {
+    path.parent() == Some(source.root())
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| is_translation(source, name))
+        && source.fs().is_dir(path)
+}
+
+/// The Contents of a Directory in One Language
+#[derive(Debug, Default)]
+pub(crate) struct Listing {
+    /// The full path of each file and directory
+    pub entries: Vec<PathBuf>,
+    /// Pages in the default language which haven't been translated
+    pub untranslated: Vec<PathBuf>,
+}
+
+/// List a Directory in the Current Language
+///
+/// Lists the directory at `dir` as it appears in the translation being read.
+/// Pages are replaced by their translations, and pages without a translation
+/// are only kept if `translation-fallback` is set. Directories and other files
+/// are shared by every language, unless the translation has its own. When
+/// reading the default language any translations are skipped.
+pub(crate) fn read_dir(source: &Source, dir: &Path) -> io::Result<Listing> {
+    let fs = source.fs();
+    let Some(code) = source.translation() else {
+        let entries = fs
+            .read_dir(dir)?
+            .into_iter()
+            .filter(|path| {
+                !is_translation_root(source, path) && split_translation(source, path).is_none()
+            })
+            .collect();
+        return Ok(Listing {
+            entries,
+            untranslated: Vec::new(),
+        });
+    };
+
+    // The directory in the default language's tree, and its counterpart in
+    // the translation's root directory.
+    let tree_path = tree_path(source, dir);
+    let original_dir = source.root().join(tree_path);
+    let translated_dir = source.root().join(code).join(tree_path);
+    if !fs.is_dir(&original_dir) && !fs.is_dir(&translated_dir) {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{:?} not found", dir),
+        ));
+    }
+
+    // Index the entries by the name of the original they stand for.
+    let name = |path: &Path| path.file_name().unwrap_or_default().to_owned();
+    let mut originals = BTreeMap::new();
+    let mut translated = BTreeMap::new();
+    for (dir, is_translated) in [(&original_dir, false), (&translated_dir, true)] {
+        if !fs.is_dir(dir) {
+            continue;
+        }
+        for path in fs.read_dir(dir)? {
+            match split_translation(source, &path) {
+                Some((original, lang)) if lang == code => {
+                    translated.insert(name(&original), path);
+                }
+                Some(_) => {}
+                None if is_translated => {
+                    translated.insert(name(&path), path);
+                }
+                None if !is_translation_root(source, &path) => {
+                    originals.insert(name(&path), path);
+                }
+                None => {}
+            }
+        }
+    }
+
+    let mut listing = Listing::default();
+    for (name, path) in originals {
+        let is_dir = fs.is_dir(&path);
+        match translated.remove(&name) {
+            // Directories are merged as they're read, so the original stands
+            // for both.
+            Some(_) if is_dir => listing.entries.push(path),
+            Some(translation) => listing.entries.push(translation),
+            None if !is_dir && is_markdown(&path) => {
+                if source.config().translation_fallback {
+                    listing.entries.push(path.clone());
+                }
+                listing.untranslated.push(path);
+            }
+            None => listing.entries.push(path),
+        }
+    }
+    listing.entries.extend(translated.into_values());
+    Ok(listing)
+}
+
+/// Check if `path` has a markdown file extension
+fn is_markdown(path: &Path) -> bool {
+    matches!(
+        utils::normalised_path_ext(path).as_deref(),
+        Some("md" | "markdown" | "mdown")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::source::MemoryFs;
+
+    fn example(config: &str) -> Source {
+        let fs = MemoryFs::with_config(
+            config,
+            &[
+                ("docs/index.md", "# Home"),
+                ("docs/index.ja.md", "# ホーム"),
+                ("docs/setup.md", "# Setup"),
+                ("docs/usage.md", "# Usage"),
+                ("docs/usage.fr.md", "# Utilisation"),
+                ("docs/logo.png", "PNG"),
+                ("docs/guide/intro.md", "# Intro"),
+                ("docs/ja/setup.md", "# セットアップ"),
+                ("docs/ja/guide/intro.md", "# はじめに"),
+                ("docs/ja/faq/index.md", "# よくある質問"),
+            ],
+        );
+        Source::open("docs", Box::new(fs)).unwrap()
+    }
+
+    const CONFIG: &str = "[languages]\nen = \"English\"\nja = \"日本語\"\nfr = \"Français\"\n";
+
+    fn entries(listing: &Listing) -> Vec<&str> {
+        let mut entries: Vec<_> = listing
+            .entries
+            .iter()
+            .map(|path| path.to_str().unwrap())
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn list_languages() {
+        let source = example(CONFIG);
+        let codes: Vec<_> = languages(&source)
+            .into_iter()
+            .map(|lang| (lang.code, lang.name))
+            .collect();
+        assert_eq!(
+            vec![
+                (String::from("en"), String::from("English")),
+                (String::from("fr"), String::from("Français")),
+                (String::from("ja"), String::from("日本語")),
+            ],
+            codes
+        );
+        assert!(languages(&example("")).is_empty());
+    }
+
+    #[test]
+    fn split_translated_paths() {
+        let source = example(CONFIG);
+        assert_eq!(
+            Some((PathBuf::from("docs/setup.md"), String::from("ja"))),
+            split_translation(&source, Path::new("docs/setup.ja.md"))
+        );
+        assert_eq!(None, split_translation(&source, Path::new("docs/setup.md")));
+        assert_eq!(
+            None,
+            split_translation(&source, Path::new("docs/setup.en.md"))
+        );
+        assert_eq!(None, split_translation(&source, Path::new("docs/v1.2.md")));
+        assert_eq!(
+            Some(String::from("setup")),
+            normalised_stem(&source, Path::new("docs/Setup.ja.md"))
+        );
+    }
+
+    #[test]
+    fn read_default_language() {
+        let source = example(CONFIG);
+        let listing = read_dir(&source, Path::new("docs")).unwrap();
+        assert_eq!(
+            vec![
+                "docs/docket.toml",
+                "docs/guide",
+                "docs/index.md",
+                "docs/logo.png",
+                "docs/setup.md",
+                "docs/usage.md",
+            ],
+            entries(&listing)
+        );
+        assert!(listing.untranslated.is_empty());
+    }
+
+    #[test]
+    fn read_translation() {
+        let mut source = example(CONFIG);
+        source.set_translation(Some("ja"));
+        let listing = read_dir(&source, Path::new("docs")).unwrap();
+        assert_eq!(
+            vec![
+                "docs/docket.toml",
+                "docs/guide",
+                "docs/index.ja.md",
+                "docs/ja/faq",
+                "docs/ja/setup.md",
+                "docs/logo.png",
+            ],
+            entries(&listing)
+        );
+        assert_eq!(vec![PathBuf::from("docs/usage.md")], listing.untranslated);
+
+        let listing = read_dir(&source, Path::new("docs/guide")).unwrap();
+        assert_eq!(vec!["docs/ja/guide/intro.md"], entries(&listing));
+        let listing = read_dir(&source, Path::new("docs/ja/faq")).unwrap();
+        assert_eq!(vec!["docs/ja/faq/index.md"], entries(&listing));
+        assert!(read_dir(&source, Path::new("docs/missing")).is_err());
+    }
+
+    #[test]
+    fn read_translation_with_fallback() {
+        let mut source = example(&format!("translation-fallback = true\n{}", CONFIG));
+        source.set_translation(Some("fr"));
+        let listing = read_dir(&source, Path::new("docs")).unwrap();
+        assert_eq!(
+            vec![
+                "docs/docket.toml",
+                "docs/guide",
+                "docs/index.md",
+                "docs/logo.png",
+                "docs/setup.md",
+                "docs/usage.fr.md",
+            ],
+            entries(&listing)
+        );
+        assert_eq!(
+            vec![
+                PathBuf::from("docs/index.md"),
+                PathBuf::from("docs/setup.md")
+            ],
+            listing.untranslated
+        );
+        assert_eq!("fr", source.config().lang);
+    }
+}
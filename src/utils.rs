@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 /// Convert a string of arbitrary charactes to a form suitable for use
 /// as an HTML identifier or file name.
@@ -26,6 +29,79 @@ pub(crate) fn slugify<S: AsRef<str>>(input: S) -> String {
         .collect()
 }
 
+/// Transliterate a Slug to ASCII
+///
+/// Accented Latin letters are replaced by their closest ASCII equivalent. Any
+/// other character which isn't safe to use unescaped in a URL is replaced
+/// with a `-`.
+pub(crate) fn ascii_slug<S: AsRef<str>>(input: S) -> String {
+    let mut slug = String::new();
+    for c in input.as_ref().chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' | '.' | '~' => slug.push(c),
+            c => slug.push_str(transliterate(c).unwrap_or("-")),
+        }
+    }
+    slug
+}
+
+/// Get the ASCII Equivalent of a Latin Letter
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' | 'Ć' | 'Č' => "C",
+        'ç' | 'ć' | 'č' => "c",
+        'Ð' | 'Ď' | 'Đ' => "D",
+        'ð' | 'ď' | 'đ' => "d",
+        'È'..='Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'Ğ' => "G",
+        'ğ' => "g",
+        'Ì'..='Ï' | 'Ī' | 'Į' | 'İ' => "I",
+        'ì'..='ï' | 'ī' | 'į' | 'ı' => "i",
+        'Ł' => "L",
+        'ł' => "l",
+        'Ñ' | 'Ń' | 'Ň' => "N",
+        'ñ' | 'ń' | 'ň' => "n",
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+        'ò'..='ö' | 'ø' | 'ō' | 'ő' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ř' => "R",
+        'ř' => "r",
+        'Ś' | 'Ş' | 'Š' => "S",
+        'ś' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'Ť' | 'Ţ' => "T",
+        'ť' | 'ţ' => "t",
+        'Þ' => "TH",
+        'þ' => "th",
+        'Ù'..='Ü' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ù'..='ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'Ý' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+/// Collapse Dashes
+///
+/// Replaces each run of `-` in the input with a single dash, and removes any
+/// from the start and end.
+pub(crate) fn collapse_dashes<S: AsRef<str>>(input: S) -> String {
+    input
+        .as_ref()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
 /// Slufigy a Path
 pub(crate) fn slugify_path<P: AsRef<Path>>(input: P) -> String {
     slugify(match input.as_ref().file_stem() {
@@ -58,14 +134,160 @@ pub(crate) fn normalised_stem<P: AsRef<Path>>(path: P) -> Option<String> {
     }
 }
 
-/// Path or Default
+/// Escape HTML
+///
+/// Returns a copy of the input with the characters that are significant in
+/// HTML text and attribute values replaced by entities. Both kinds of quote
+/// are escaped, so the result is safe within either kind of attribute.
+pub(crate) fn escape_html<S: AsRef<str>>(input: S) -> String {
+    let mut escaped = String::new();
+    // Writing to a `String` is infallible.
+    let _ = pulldown_cmark::escape::escape_html(&mut escaped, input.as_ref());
+    escaped.replace('\'', "&#39;")
+}
+
+/// Convert HTML to Plain Text
+///
+/// Drops any tags from `html`, decodes the entities that `escape_html`
+/// produces, and collapses runs of whitespace to a single space.
+pub(crate) fn html_to_text<S: AsRef<str>>(html: S) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.as_ref().chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Truncate Text to a Number of Characters
+///
+/// If `text` is longer than `max` characters it is cut at the last word
+/// boundary at or before `max`, and an ellipsis added.
+pub(crate) fn truncate_words(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_owned();
+    }
+    let cut = text
+        .char_indices()
+        .nth(max)
+        .map(|(idx, _)| idx)
+        .unwrap_or(text.len());
+    // If the text breaks straight after the last character that fits then
+    // the whole of the last word fits.
+    let truncated = if text[cut..].starts_with(char::is_whitespace) {
+        &text[..cut]
+    } else {
+        match text[..cut].rfind(char::is_whitespace) {
+            Some(space) => &text[..space],
+            None => &text[..cut],
+        }
+    };
+    format!(
+        "{}…",
+        truncated.trim_end_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+    )
+}
+
+/// URL Encode a Relative Path
+///
+/// Joins the components of the path with `/` and percent-encodes any bytes
+/// which aren't safe to use unescaped within a URL path.
+pub(crate) fn url_encode_path<P: AsRef<Path>>(path: P) -> String {
+    let mut encoded = String::new();
+    for (idx, component) in path.as_ref().iter().enumerate() {
+        if idx > 0 {
+            encoded.push('/');
+        }
+        for byte in component.to_string_lossy().bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    encoded.push(byte as char)
+                }
+                _ => encoded.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+    }
+    encoded
+}
+
+/// Convert a `SystemTime` to a `YYYY-MM-DD` date string. Returns `None` for
+/// times before the Unix epoch.
+pub(crate) fn format_date(t: SystemTime) -> Option<String> {
+    let (y, m, d) = civil_date(t)?;
+    Some(format!("{:04}-{:02}-{:02}", y, m, d))
+}
+
+/// Get the year, month, and day of a `SystemTime` in UTC. Returns `None` for
+/// times before the Unix epoch.
+pub(crate) fn civil_date(t: SystemTime) -> Option<(i64, u64, u64)> {
+    let secs = t.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    Some(civil_from_days(secs / 86400))
+}
+
+/// Convert a `SystemTime` to an RFC 3339 timestamp in UTC, such as
+/// `2023-11-14T22:13:20Z`. Returns `None` for times before the Unix epoch.
+pub(crate) fn format_timestamp(t: SystemTime) -> Option<String> {
+    let secs = t.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    let (y, m, d) = civil_from_days(secs / 86400);
+    let time = secs % 86400;
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        y,
+        m,
+        d,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    ))
+}
+
+/// Get the `SystemTime` for a calendar date, plus a number of `seconds` into
+/// that day. Returns `None` for times before the Unix epoch.
+pub(crate) fn time_from_civil(year: i64, month: u64, day: u64, seconds: i64) -> Option<SystemTime> {
+    let secs = days_from_civil(year, month, day) * 86400 + seconds;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+/// Convert a count of days since the Unix epoch to a year, month, and day.
+///
+/// Uses Howard Hinnant's `civil_from_days` algorithm to avoid any external
+/// date/time dependency.
+fn civil_from_days(days: i64) -> (i64, u64, u64) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = if m <= 2 { y + 1 } else { y };
+    (y, m, d)
+}
+
+/// Convert a year, month, and day to a count of days since the Unix epoch.
 ///
-/// Try to convert a command line argument to a path. Falling back to the
-/// default if none is provided.
-pub(crate) fn path_or_default(maybe_path: Option<String>, default: &str) -> PathBuf {
-    maybe_path
-        .map(PathBuf::from)
-        .unwrap_or_else(|| default.to_owned().into())
+/// The inverse of `civil_from_days`, from the same set of algorithms.
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = (y - era * 400) as u64;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe as i64 - 719468
 }
 
 /// Prettify a Directory Name
@@ -119,6 +341,22 @@ mod test {
         assert_eq!("ü¬é∆∂", slugify("ü¬é∆∂"));
     }
 
+    #[test]
+    fn ascii_slug_transliterates() {
+        assert_eq!("creme-brulee", ascii_slug("crème-brûlée"));
+        assert_eq!("Strasse", ascii_slug("Straße"));
+        assert_eq!("--------", ascii_slug("€$§±*∆∂¬"));
+        assert_eq!("human2.0_~x", ascii_slug("human2.0_~x"));
+    }
+
+    #[test]
+    fn collapse_dashes_in_slugs() {
+        assert_eq!("a-b", collapse_dashes("a---b"));
+        assert_eq!("a-b-c", collapse_dashes("-a-b--c-"));
+        assert_eq!("", collapse_dashes("---"));
+        assert_eq!("hello", collapse_dashes("hello"));
+    }
+
     #[test]
     fn slufigy_removes_leading_numbers() {
         assert_eq!("hello", slugify("01-hello"));
@@ -173,6 +411,69 @@ mod test {
         );
     }
 
+    #[test]
+    fn convert_html_to_text() {
+        assert_eq!(
+            "I am an H1 & <friends>",
+            html_to_text("I am <code>an</code> <strong>H1</strong> &amp; &lt;friends&gt;")
+        );
+        assert_eq!(
+            "First line. Second \"line\".",
+            html_to_text("<p>First line.\nSecond &quot;line&quot;.</p>\n")
+        );
+    }
+
+    #[test]
+    fn truncate_long_text() {
+        assert_eq!("short", truncate_words("short", 10));
+        assert_eq!("Some…", truncate_words("Some longer text", 10));
+        assert_eq!("Some, longer…", truncate_words("Some, longer, text", 14));
+        assert_eq!("Averylongw…", truncate_words("Averylongword", 10));
+    }
+
+    #[test]
+    fn truncate_at_word_boundary() {
+        assert_eq!("Some longer…", truncate_words("Some longer text", 11));
+        assert_eq!("Some longer…", truncate_words("Some longer text", 12));
+        assert_eq!("Some…", truncate_words("Some longer text", 10));
+    }
+
+    #[test]
+    fn format_dates() {
+        assert_eq!(Some(String::from("1970-01-01")), format_date(UNIX_EPOCH));
+        assert_eq!(
+            Some(String::from("2023-11-14")),
+            format_date(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(None, format_date(UNIX_EPOCH - Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn format_timestamps() {
+        assert_eq!(
+            Some(String::from("2023-11-14T22:13:20Z")),
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(
+            Some(String::from("1970-01-01T00:00:00Z")),
+            format_timestamp(UNIX_EPOCH)
+        );
+    }
+
+    #[test]
+    fn times_from_civil_dates() {
+        assert_eq!(Some(UNIX_EPOCH), time_from_civil(1970, 1, 1, 0));
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            time_from_civil(2023, 11, 14, 22 * 3600 + 13 * 60 + 20)
+        );
+        assert_eq!(
+            Some(String::from("2024-02-29")),
+            time_from_civil(2024, 2, 29, 0).and_then(format_date)
+        );
+        assert_eq!(None, time_from_civil(1969, 12, 31, 0));
+    }
+
     #[test]
     fn prettified_dirnames() {
         assert_eq!(Some("Hello World"), prettify_dir("Hello World").as_deref());
@@ -185,14 +486,26 @@ mod test {
     }
 
     #[test]
-    fn path_or_default_with_valid_argument() {
-        let source = Some("/Users/foo/".to_owned());
-        assert_eq!(Path::new("/Users/foo/"), path_or_default(source, "."));
+    fn escape_html_replaces_special_characters() {
+        assert_eq!("plain text", escape_html("plain text"));
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;",
+            escape_html("<a href=\"x\">&</a>")
+        );
+        assert_eq!(
+            "&lt;a href=&#39;x&#39;&gt;&lt;/a&gt;",
+            escape_html("<a href='x'></a>")
+        );
     }
 
     #[test]
-    fn path_or_default_without_argument() {
-        let source = None;
-        assert_eq!(Path::new("baz/"), path_or_default(source, "baz/"));
+    fn url_encode_path_joins_components() {
+        assert_eq!("index.md", url_encode_path("index.md"));
+        assert_eq!(
+            "a/b/c.md",
+            url_encode_path(Path::new("a").join("b").join("c.md"))
+        );
+        assert_eq!("Hello%20World%3F.md", url_encode_path("Hello World?.md"));
+        assert_eq!("caf%C3%A9.md", url_encode_path("café.md"));
     }
 }